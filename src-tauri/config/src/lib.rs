@@ -0,0 +1,704 @@
+//! Configuration shared by the NeoDLP app and `neodlp-msghost`, stored as JSON in
+//! `msghost-config.json` inside the user's config directory.
+//!
+//! The file carries a `version`. Older files are migrated forward when loaded,
+//! missing fields take their defaults and every config is validated before use.
+//! Writes replace the file atomically and keep the previous valid config as a
+//! backup, which is loaded instead of a file that got corrupted.
+//!
+//! Several instances can run side by side: `NEODLP_CONFIG_DIR` moves the config
+//! directory, `--config <path>` points at another config file and `NEODLP_WS_PORT`
+//! overrides the port without touching the file.
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Version written by this build. Bump it and add a step to `migrate` whenever
+/// a field is moved or renamed.
+pub const CONFIG_VERSION: u32 = 2;
+pub const CONFIG_FILE: &str = "msghost-config.json";
+/// Shared secret `neodlp-msghost` presents in the `hello` handshake, created by the app.
+pub const TOKEN_FILE: &str = "msghost-token";
+/// The app's `LaunchCommand`, recorded on every launch so `neodlp-msghost` can start it.
+pub const LAUNCH_FILE: &str = "app-launch.json";
+/// Port the app's WebSocket server is actually bound to, rewritten whenever it starts.
+/// Runtime state kept apart from `CONFIG_FILE`, it differs from the configured port
+/// after a fallback.
+pub const PORT_FILE: &str = "app-port";
+pub const DEFAULT_PORT: u16 = 53511;
+const DEFAULT_PORT_RANGE_END: u16 = 53530;
+
+pub const CONFIG_DIR_ENV: &str = "NEODLP_CONFIG_DIR";
+pub const PORT_ENV: &str = "NEODLP_WS_PORT";
+pub const CONFIG_ARG: &str = "--config";
+
+/// Command that starts this installation of the app, stored in `LAUNCH_FILE`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LaunchCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+/// Where an effective setting comes from, the later ones win.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSource {
+    Default,
+    File,
+    Environment,
+    Argument,
+    /// The configured port was taken, the server moved to a free one of the fallback range
+    Fallback,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub download: DownloadConfig,
+    pub network: NetworkConfig,
+    pub extension: ExtensionConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Empty for the system downloads directory
+    pub download_dir: String,
+    pub max_parallel_downloads: u32,
+    pub max_retries: u32,
+    pub auto_resume_downloads: bool,
+    pub filename_template: String,
+    /// Seconds yt-dlp gets to flush its `.part` files when the app quits
+    pub shutdown_timeout: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InternetProtocol {
+    Ipv4,
+    Ipv6,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub use_proxy: bool,
+    pub proxy_url: String,
+    pub use_rate_limit: bool,
+    /// In bytes per second
+    pub rate_limit: u64,
+    pub use_force_internet_protocol: bool,
+    pub force_internet_protocol: InternetProtocol,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExtensionConfig {
+    /// Port of the WebSocket server `neodlp-msghost` connects to
+    pub port: u16,
+    /// Bind the first free port of the range when `port` is taken, `port` itself is kept
+    pub port_fallback: bool,
+    pub port_range_start: u16,
+    pub port_range_end: u16,
+}
+
+// Defaults match the frontend's settings defaults
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            download: DownloadConfig::default(),
+            network: NetworkConfig::default(),
+            extension: ExtensionConfig::default(),
+        }
+    }
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            download_dir: String::new(),
+            max_parallel_downloads: 2,
+            max_retries: 5,
+            auto_resume_downloads: true,
+            filename_template: "%(title|Untitled)s_%(resolution|unknown)s".to_string(),
+            shutdown_timeout: 10,
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            use_proxy: false,
+            proxy_url: String::new(),
+            use_rate_limit: false,
+            rate_limit: 1024 * 1024,
+            use_force_internet_protocol: false,
+            force_internet_protocol: InternetProtocol::Ipv4,
+        }
+    }
+}
+
+impl Default for ExtensionConfig {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            port_fallback: false,
+            port_range_start: DEFAULT_PORT,
+            port_range_end: DEFAULT_PORT_RANGE_END,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values the frontend forms also enforce, so hand edited files
+    /// can't put the app in a state the UI can't represent.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != CONFIG_VERSION {
+            return Err(format!("Unsupported config version {}", self.version));
+        }
+        if !(1..=5).contains(&self.download.max_parallel_downloads) {
+            return Err("download.max_parallel_downloads must be between 1 and 5".to_string());
+        }
+        if !(1..=100).contains(&self.download.max_retries) {
+            return Err("download.max_retries must be between 1 and 100".to_string());
+        }
+        if !(1..=60).contains(&self.download.shutdown_timeout) {
+            return Err("download.shutdown_timeout must be between 1 and 60".to_string());
+        }
+        if self.download.filename_template.trim().is_empty() {
+            return Err("download.filename_template must not be empty".to_string());
+        }
+        if !self.network.proxy_url.is_empty() && !self.network.proxy_url.contains("://") {
+            return Err("network.proxy_url must be a URL".to_string());
+        }
+        if !(1024..=100 * 1024 * 1024).contains(&self.network.rate_limit) {
+            return Err("network.rate_limit must be between 1024 and 104857600".to_string());
+        }
+        if self.extension.port < 1024 {
+            return Err("extension.port must be at least 1024".to_string());
+        }
+        if self.extension.port_range_start < 1024
+            || self.extension.port_range_start > self.extension.port_range_end
+        {
+            return Err(
+                "extension.port_range_start must be at least 1024 and not after port_range_end"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Port the WebSocket server listens on, `NEODLP_WS_PORT` wins over the file.
+    pub fn effective_port(&self) -> u16 {
+        get_port_override().unwrap_or(self.extension.port)
+    }
+
+    /// Ports the WebSocket server may listen on, in the order to try them. Only the
+    /// effective port unless the fallback is enabled, a port from `NEODLP_WS_PORT` is never
+    /// swapped for another one.
+    pub fn websocket_ports(&self) -> Vec<u16> {
+        self.websocket_ports_with(get_port_override())
+    }
+
+    // `websocket_ports` for the given `NEODLP_WS_PORT` value
+    fn websocket_ports_with(&self, port_override: Option<u16>) -> Vec<u16> {
+        let port = port_override.unwrap_or(self.extension.port);
+        let mut ports = vec![port];
+        if self.extension.port_fallback && port_override.is_none() {
+            ports.extend(
+                (self.extension.port_range_start..=self.extension.port_range_end)
+                    .filter(|candidate| *candidate != port),
+            );
+        }
+        ports
+    }
+}
+
+/// Port from `NEODLP_WS_PORT`, `None` if unset or not a valid port.
+pub fn get_port_override() -> Option<u16> {
+    env::var(PORT_ENV).ok().and_then(|port| parse_port(&port))
+}
+
+fn parse_port(port: &str) -> Option<u16> {
+    port.trim().parse::<u16>().ok().filter(|port| *port >= 1024)
+}
+
+// Accepts both `--config <path>` and `--config=<path>`
+fn find_config_arg(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_ARG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg
+            .to_str()
+            .and_then(|arg| arg.strip_prefix(CONFIG_ARG)?.strip_prefix('='))
+        {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+fn get_config_dir_override() -> Option<PathBuf> {
+    env::var_os(CONFIG_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Directory of the config and of the files shared with `neodlp-msghost`.
+pub fn get_config_dir() -> Option<PathBuf> {
+    get_config_dir_override().or_else(|| {
+        ProjectDirs::from("com", "neosubhamoy", "neodlp")
+            .map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+    })
+}
+
+pub fn get_config_path() -> Option<PathBuf> {
+    find_config_arg(env::args_os().skip(1))
+        .or_else(|| get_config_dir().map(|dir| dir.join(CONFIG_FILE)))
+}
+
+/// Where the config path was taken from.
+pub fn get_config_path_source() -> ValueSource {
+    if find_config_arg(env::args_os().skip(1)).is_some() {
+        ValueSource::Argument
+    } else if get_config_dir_override().is_some() {
+        ValueSource::Environment
+    } else {
+        ValueSource::Default
+    }
+}
+
+/// Where the effective port was taken from.
+pub fn get_port_source() -> ValueSource {
+    if get_port_override().is_some() {
+        ValueSource::Environment
+    } else if get_config_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .is_some_and(|content| file_sets_port(&content))
+    {
+        ValueSource::File
+    } else {
+        ValueSource::Default
+    }
+}
+
+// The backup and temp files live next to the config, wherever that is
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Version of a config file, files written before versioning have none.
+pub fn file_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version as u32)
+}
+
+// Each step takes a file of the previous version, fields it doesn't know are kept
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let version = file_version(&value);
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "Config version {} is newer than supported version {}",
+            version, CONFIG_VERSION
+        ));
+    }
+
+    // 1: `{"port": ...}`, only read by the extension bridge
+    if version < 2 {
+        let mut extension = Map::new();
+        if let Some(port) = value
+            .as_object_mut()
+            .and_then(|object| object.remove("port"))
+        {
+            extension.insert("port".to_string(), port);
+        }
+        value["extension"] = Value::Object(extension);
+    }
+
+    value["version"] = Value::from(CONFIG_VERSION);
+    Ok(value)
+}
+
+// Whether a config file is used and sets the port, rather than leaving it at its default
+fn file_sets_port(content: &str) -> bool {
+    parse_config(content).is_ok()
+        && serde_json::from_str(content)
+            .ok()
+            .and_then(|value| migrate(value).ok())
+            .is_some_and(|value| value.pointer("/extension/port").is_some())
+}
+
+/// Parses, migrates and validates the content of a config file.
+pub fn parse_config(content: &str) -> Result<Config, String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("Invalid config file: {}", e))?;
+    if !value.is_object() {
+        return Err("Invalid config file: expected a JSON object".to_string());
+    }
+    let config: Config = serde_json::from_value(migrate(value)?)
+        .map_err(|e| format!("Invalid config file: {}", e))?;
+    config.validate()?;
+    Ok(config)
+}
+
+fn read_config(path: &Path) -> Result<Option<Config>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_config(&content).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read config file: {}", e)),
+    }
+}
+
+fn load_from_path(config_path: &Path) -> (Config, Option<String>) {
+    let error = match read_config(config_path) {
+        Ok(Some(config)) => return (config, None),
+        Ok(None) => return (Config::default(), None),
+        Err(e) => e,
+    };
+    match read_config(&with_suffix(config_path, ".bak")) {
+        Ok(Some(config)) => (config, Some(format!("{}, using the backup", error))),
+        _ => (
+            Config::default(),
+            Some(format!("{}, using the defaults", error)),
+        ),
+    }
+}
+
+/// Loads the config file, or its backup if the file can't be used, or the defaults.
+/// Also returns why the config file itself couldn't be used, a missing file is not an error.
+pub fn load_config_checked() -> (Config, Option<String>) {
+    match get_config_path() {
+        Some(config_path) => load_from_path(&config_path),
+        None => (
+            Config::default(),
+            Some("Could not determine config path".to_string()),
+        ),
+    }
+}
+
+pub fn load_config() -> Config {
+    load_config_checked().0
+}
+
+// The temporary file is flushed to disk before it replaces the config, so a crash
+// leaves either the old or the new file but never a truncated one
+fn write_atomically(path: &Path, temp_path: &Path, content: &str) -> std::io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(temp_path, path)?;
+
+    // Persist the rename itself, directories can't be opened like this on Windows
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn save_to_path(config_path: &Path, config: &Config) -> Result<(), String> {
+    config.validate()?;
+
+    if let Some(config_dir) = config_path.parent() {
+        fs::create_dir_all(config_dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    // Only a config that still loads is worth keeping, a broken one would replace a good backup
+    if let Ok(Some(_)) = read_config(config_path) {
+        fs::copy(config_path, with_suffix(config_path, ".bak"))
+            .map_err(|e| format!("Failed to back up config file: {}", e))?;
+    }
+
+    write_atomically(config_path, &with_suffix(config_path, ".tmp"), &content)
+        .map_err(|e| format!("Failed to write config file: {}", e))?;
+
+    Ok(())
+}
+
+pub fn save_config(config: &Config) -> Result<(), String> {
+    let config_path =
+        get_config_path().ok_or_else(|| "Could not determine config path".to_string())?;
+    save_to_path(&config_path, config)
+}
+
+// Rejects typos, they would otherwise be dropped silently by `Deserialize`
+fn check_known_fields(patch: &Value, reference: &Value, path: &str) -> Result<(), String> {
+    let (Value::Object(patch), Value::Object(reference)) = (patch, reference) else {
+        return Ok(());
+    };
+    for (key, value) in patch {
+        let field = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        let known = reference
+            .get(key)
+            .ok_or_else(|| format!("Unknown config field {}", field))?;
+        check_known_fields(value, known, &field)?;
+    }
+    Ok(())
+}
+
+// JSON Merge Patch (RFC 7396), `null` removes a field so it takes its default again
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Returns `config` with `patch` (a JSON merge patch) applied, or why it was rejected.
+pub fn apply_patch(config: &Config, patch: &Value) -> Result<Config, String> {
+    if !patch.is_object() {
+        return Err("Config patch must be a JSON object".to_string());
+    }
+    let mut value = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    check_known_fields(patch, &value, "")?;
+    merge_patch(&mut value, patch);
+
+    let mut patched: Config =
+        serde_json::from_value(value).map_err(|e| format!("Invalid config patch: {}", e))?;
+    patched.version = CONFIG_VERSION;
+    patched.validate()?;
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_unversioned_port_only_file() {
+        let config = parse_config(r#"{"port": 53600}"#).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.extension.port, 53600);
+        assert_eq!(config.download, DownloadConfig::default());
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let config = parse_config(r#"{"version": 2, "network": {"use_proxy": false}}"#).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_invalid_values() {
+        assert!(parse_config(r#"{"version": 99}"#).is_err());
+        assert!(parse_config(r#"{"port": 80}"#).is_err());
+        assert!(parse_config(r#"[53511]"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "network": {"proxy_url": "localhost"}}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "download": {"shutdown_timeout": 0}}"#).is_err());
+        assert!(parse_config(
+            r#"{"version": 2, "extension": {"port_range_start": 53600, "port_range_end": 53500}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn round_trips_through_the_file_format() {
+        let mut config = Config::default();
+        config.network.force_internet_protocol = InternetProtocol::Ipv6;
+        let content = serde_json::to_string_pretty(&config).unwrap();
+        assert_eq!(parse_config(&content).unwrap(), config);
+    }
+
+    #[test]
+    fn patches_nested_fields_only() {
+        let config = apply_patch(
+            &Config::default(),
+            &json!({"download": {"max_parallel_downloads": 4}, "extension": {"port": 53600}}),
+        )
+        .unwrap();
+        assert_eq!(config.download.max_parallel_downloads, 4);
+        assert_eq!(config.download.max_retries, 5);
+        assert_eq!(config.extension.port, 53600);
+    }
+
+    #[test]
+    fn null_resets_a_field_to_its_default() {
+        let mut config = Config::default();
+        config.extension.port = 53600;
+        let config = apply_patch(&config, &json!({"extension": {"port": null}})).unwrap();
+        assert_eq!(config.extension.port, DEFAULT_PORT);
+    }
+
+    #[test]
+    fn rejects_unknown_and_invalid_patches() {
+        let config = Config::default();
+        assert_eq!(
+            apply_patch(&config, &json!({"extention": {"port": 53600}})).unwrap_err(),
+            "Unknown config field extention"
+        );
+        assert!(apply_patch(&config, &json!({"download": {"max_retries": 0}})).is_err());
+        assert!(apply_patch(&config, &json!({"extension": {"port": "53600"}})).is_err());
+        assert!(apply_patch(&config, &json!(42)).is_err());
+    }
+
+    #[test]
+    fn websocket_ports_walk_the_range_only_with_the_fallback() {
+        let mut config = Config::default();
+        config.extension.port = 53515;
+        config.extension.port_range_start = 53513;
+        config.extension.port_range_end = 53516;
+        assert_eq!(config.websocket_ports_with(None), vec![53515]);
+
+        config.extension.port_fallback = true;
+        assert_eq!(
+            config.websocket_ports_with(None),
+            vec![53515, 53513, 53514, 53516]
+        );
+        assert_eq!(config.websocket_ports_with(Some(53600)), vec![53600]);
+    }
+
+    #[test]
+    fn port_source_is_the_file_only_when_it_sets_the_port() {
+        assert!(file_sets_port(
+            r#"{"version": 2, "extension": {"port": 53600}}"#
+        ));
+        assert!(file_sets_port(r#"{"port": 53600}"#));
+        assert!(!file_sets_port(
+            r#"{"version": 2, "extension": {"port_fallback": true}}"#
+        ));
+        assert!(!file_sets_port(
+            r#"{"version": 2, "network": {"use_proxy": true}}"#
+        ));
+        assert!(!file_sets_port(
+            r#"{"version": 2, "extension": {"port": 80}}"#
+        ));
+    }
+
+    fn temp_config_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("neodlp-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn missing_file_loads_defaults_without_error() {
+        let path = temp_config_dir("missing").join(CONFIG_FILE);
+        assert_eq!(load_from_path(&path), (Config::default(), None));
+    }
+
+    #[test]
+    fn save_keeps_the_previous_config_as_backup() {
+        let dir = temp_config_dir("backup");
+        let path = dir.join(CONFIG_FILE);
+        let mut config = Config::default();
+        config.extension.port = 53600;
+        save_to_path(&path, &config).unwrap();
+        config.extension.port = 53700;
+        save_to_path(&path, &config).unwrap();
+
+        assert_eq!(load_from_path(&path), (config, None));
+        let backup =
+            parse_config(&fs::read_to_string(dir.join("msghost-config.json.bak")).unwrap())
+                .unwrap();
+        assert_eq!(backup.extension.port, 53600);
+        assert!(!dir.join("msghost-config.json.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupted_file_falls_back_to_the_backup() {
+        let dir = temp_config_dir("corrupted");
+        let path = dir.join("custom.json");
+        let mut config = Config::default();
+        config.extension.port = 53600;
+        save_to_path(&path, &config).unwrap();
+        save_to_path(&path, &config).unwrap();
+        fs::write(&path, r#"{"version": 2, "extens"#).unwrap();
+
+        let (loaded, error) = load_from_path(&path);
+        assert_eq!(loaded, config);
+        assert!(error.unwrap().ends_with("using the backup"));
+
+        // Saving over the broken file must not replace the good backup
+        save_to_path(&path, &Config::default()).unwrap();
+        let backup =
+            parse_config(&fs::read_to_string(dir.join("custom.json.bak")).unwrap()).unwrap();
+        assert_eq!(backup, config);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupted_file_without_backup_falls_back_to_defaults() {
+        let dir = temp_config_dir("no-backup");
+        let path = dir.join(CONFIG_FILE);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "").unwrap();
+
+        let (loaded, error) = load_from_path(&path);
+        assert_eq!(loaded, Config::default());
+        assert!(error.unwrap().ends_with("using the defaults"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_argument_is_found_in_both_forms() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            find_config_arg(args(&["--minimized", "--config", "/tmp/a.json"])),
+            Some(PathBuf::from("/tmp/a.json"))
+        );
+        assert_eq!(
+            find_config_arg(args(&["--config=/tmp/b.json"])),
+            Some(PathBuf::from("/tmp/b.json"))
+        );
+        assert_eq!(find_config_arg(args(&["--config"])), None);
+        assert_eq!(find_config_arg(args(&["--configure"])), None);
+    }
+
+    #[test]
+    fn port_override_must_be_a_valid_port() {
+        assert_eq!(parse_port(" 53600 "), Some(53600));
+        assert_eq!(parse_port("80"), None);
+        assert_eq!(parse_port("70000"), None);
+        assert_eq!(parse_port("port"), None);
+    }
+
+    #[test]
+    fn patch_cannot_change_the_version() {
+        let config = apply_patch(&Config::default(), &json!({"version": 1})).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+    }
+}
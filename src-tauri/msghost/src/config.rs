@@ -1,35 +1,28 @@
-use directories::ProjectDirs;
-use serde::{Deserialize, Serialize};
+use neodlp_config::{get_config_dir, LaunchCommand, LAUNCH_FILE, PORT_FILE, TOKEN_FILE};
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Config {
-    pub port: u16,
-}
+pub use neodlp_config::load_config;
 
-impl Default for Config {
-    fn default() -> Self {
-        Self { port: 53511 }
-    }
+pub fn get_token_path() -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join(TOKEN_FILE))
 }
 
-pub fn get_config_dir() -> Option<PathBuf> {
-    ProjectDirs::from("com", "neosubhamoy", "neodlp")
-        .map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+// Missing until the app has run once, the handshake is then rejected as unauthorized
+pub fn load_token() -> Option<String> {
+    let token = fs::read_to_string(get_token_path()?).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
 }
 
-pub fn get_config_path() -> Option<PathBuf> {
-    get_config_dir().map(|dir| dir.join("msghost-config.json"))
+// Missing until the app has run once, it can't be launched before that
+pub fn load_launch_command() -> Option<LaunchCommand> {
+    let content = fs::read_to_string(get_config_dir()?.join(LAUNCH_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
 }
 
-pub fn load_config() -> Config {
-    if let Some(config_path) = get_config_path() {
-        if let Ok(content) = fs::read_to_string(config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
-            }
-        }
-    }
-    Config::default()
+// Missing until the app's server has started once, and stale after it exits
+pub fn load_active_port() -> Option<u16> {
+    let port = fs::read_to_string(get_config_dir()?.join(PORT_FILE)).ok()?;
+    port.trim().parse().ok()
 }
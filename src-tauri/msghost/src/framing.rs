@@ -0,0 +1,192 @@
+//! Native messaging framing: every message is UTF-8 JSON preceded by its length
+//! as a 32-bit integer in native byte order.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Largest message accepted from the browser. Requests are small JSON objects,
+/// anything bigger is skipped instead of being buffered.
+pub const MAX_INCOMING_MESSAGE_SIZE: usize = 1024 * 1024;
+/// Largest message browsers accept from a native messaging host (Chrome's limit).
+pub const MAX_OUTGOING_MESSAGE_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum FrameError {
+    /// The message was skipped (incoming) or not written (outgoing), the stream is still usable
+    TooLarge(usize),
+    /// The message was read in full, the stream is still usable
+    InvalidUtf8,
+    Io(io::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooLarge(size) => write!(f, "Message of {} bytes is too large", size),
+            FrameError::InvalidUtf8 => write!(f, "Message is not valid UTF-8"),
+            FrameError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<io::Error> for FrameError {
+    fn from(error: io::Error) -> Self {
+        FrameError::Io(error)
+    }
+}
+
+/// Reads one message. Returns `None` when the browser closed the stream between
+/// two messages, a stream closed in the middle of a message is an error.
+pub fn read_message<R: Read>(reader: &mut R) -> Result<Option<String>, FrameError> {
+    let mut length_bytes = [0u8; 4];
+    let mut filled = 0;
+    while filled < length_bytes.len() {
+        match reader.read(&mut length_bytes[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let length = u32::from_ne_bytes(length_bytes) as usize;
+
+    if length > MAX_INCOMING_MESSAGE_SIZE {
+        // Skip the body so the next message is read from its length prefix
+        let skipped = io::copy(&mut reader.take(length as u64), &mut io::sink())?;
+        if skipped < length as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        return Err(FrameError::TooLarge(length));
+    }
+
+    let mut buffer = vec![0u8; length];
+    reader.read_exact(&mut buffer)?;
+    String::from_utf8(buffer)
+        .map(Some)
+        .map_err(|_| FrameError::InvalidUtf8)
+}
+
+/// Writes one message, nothing is written if it is over the browser's limit.
+pub fn write_message<W: Write>(writer: &mut W, message: &str) -> Result<(), FrameError> {
+    let message_bytes = message.as_bytes();
+    if message_bytes.len() > MAX_OUTGOING_MESSAGE_SIZE {
+        return Err(FrameError::TooLarge(message_bytes.len()));
+    }
+    writer.write_all(&(message_bytes.len() as u32).to_ne_bytes())?;
+    writer.write_all(message_bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(body: &[u8]) -> Vec<u8> {
+        let mut bytes = (body.len() as u32).to_ne_bytes().to_vec();
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn reads_consecutive_messages_until_eof() {
+        let mut input = frame(br#"{"command":"ping"}"#);
+        input.extend(frame(br#"{"command":"status"}"#));
+        let mut reader = Cursor::new(input);
+
+        assert_eq!(
+            read_message(&mut reader).unwrap().as_deref(),
+            Some(r#"{"command":"ping"}"#)
+        );
+        assert_eq!(
+            read_message(&mut reader).unwrap().as_deref(),
+            Some(r#"{"command":"status"}"#)
+        );
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn empty_input_is_a_clean_eof() {
+        assert!(read_message(&mut Cursor::new(Vec::new()))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn truncated_length_is_an_error() {
+        let mut reader = Cursor::new(vec![4u8, 0]);
+        assert!(matches!(read_message(&mut reader), Err(FrameError::Io(_))));
+    }
+
+    #[test]
+    fn truncated_body_is_an_error() {
+        let mut input = frame(b"{}");
+        input.pop();
+        assert!(matches!(
+            read_message(&mut Cursor::new(input)),
+            Err(FrameError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_message_is_skipped() {
+        let mut input = frame(&vec![b' '; MAX_INCOMING_MESSAGE_SIZE + 1]);
+        input.extend(frame(b"{}"));
+        let mut reader = Cursor::new(input);
+
+        assert!(matches!(
+            read_message(&mut reader),
+            Err(FrameError::TooLarge(size)) if size == MAX_INCOMING_MESSAGE_SIZE + 1
+        ));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn huge_length_is_rejected_without_allocating() {
+        let mut reader = Cursor::new(u32::MAX.to_ne_bytes().to_vec());
+        assert!(matches!(read_message(&mut reader), Err(FrameError::Io(_))));
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_the_stream_usable() {
+        let mut input = frame(&[0xff, 0xfe]);
+        input.extend(frame(b"{}"));
+        let mut reader = Cursor::new(input);
+
+        assert!(matches!(
+            read_message(&mut reader),
+            Err(FrameError::InvalidUtf8)
+        ));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn writes_length_prefixed_message() {
+        let mut output = Vec::new();
+        write_message(&mut output, r#"{"type":"pong"}"#).unwrap();
+        assert_eq!(output, frame(br#"{"type":"pong"}"#));
+    }
+
+    #[test]
+    fn refuses_to_write_oversized_message() {
+        let mut output = Vec::new();
+        let message = " ".repeat(MAX_OUTGOING_MESSAGE_SIZE + 1);
+        assert!(matches!(
+            write_message(&mut output, &message),
+            Err(FrameError::TooLarge(_))
+        ));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_the_framing() {
+        let mut output = Vec::new();
+        write_message(&mut output, "héllo").unwrap();
+        let mut reader = Cursor::new(output);
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("héllo"));
+    }
+}
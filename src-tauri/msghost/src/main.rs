@@ -1,84 +1,280 @@
 mod config;
-use config::load_config;
+mod framing;
+use config::{load_active_port, load_config, load_launch_command, load_token};
+use framing::{read_message, write_message, FrameError};
 use futures_util::{SinkExt, StreamExt};
-use serde_json::Value;
-use std::io::{self, Read, Write};
-use std::time::Duration;
+use neodlp_protocol::{
+    is_compatible, parse_request, parse_request_id, ErrorCode, ProtocolError, Request,
+    RequestFrame, Response, ResponseFrame, PROTOCOL_VERSION,
+};
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tokio_tungstenite::{
     connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
 };
 
-fn get_websocket_url() -> String {
-    let config = load_config();
-    format!("ws://localhost:{}", config.port)
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// How long to wait for the WebSocket server of an app started by the host
+const APP_START_TIMEOUT: Duration = Duration::from_secs(30);
+const APP_START_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn get_websocket_url(port: u16) -> String {
+    format!("ws://localhost:{}", port)
 }
 
-async fn connect_with_retry(
-    url: &str,
-    max_attempts: u32,
-) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error + Send + Sync>> {
-    let mut attempts = 0;
-    loop {
-        match connect_async(url).await {
-            Ok((ws_stream, _)) => {
-                eprintln!("Successfully connected to Tauri app :)");
-                return Ok(ws_stream);
-            }
-            Err(e) => {
-                attempts += 1;
-                if attempts >= max_attempts {
-                    return Err(Box::new(e));
-                }
-                let wait_time = Duration::from_secs(2u64.pow(attempts));
-                eprintln!(
-                    "Connection attempt {} failed. Retrying in {:?}...",
-                    attempts, wait_time
-                );
-                sleep(wait_time).await;
+// The port the app recorded when its server started, then the configured port and the
+// fallback range it may have moved to. Both files are read again every time.
+fn candidate_ports() -> Vec<u16> {
+    let mut ports: Vec<u16> = load_active_port().into_iter().collect();
+    for port in load_config().websocket_ports() {
+        if !ports.contains(&port) {
+            ports.push(port);
+        }
+    }
+    ports
+}
+
+// Goes on to the next port when the handshake is rejected, the first server that answers
+// isn't necessarily this app (e.g. another instance with its own token). Fails with the
+// last rejection, or `None` if no server answered.
+async fn connect_any_port() -> Result<(WsStream, Response), Option<Response>> {
+    let mut rejection = None;
+    for port in candidate_ports() {
+        let Ok((mut ws_stream, _)) = connect_async(get_websocket_url(port)).await else {
+            continue;
+        };
+        match authenticate(&mut ws_stream).await {
+            Ok(hello) => return Ok((ws_stream, hello)),
+            Err(response) => {
+                eprintln!("Handshake rejected on port {}", port);
+                rejection = Some(response);
             }
         }
     }
+    Err(rejection)
 }
 
-fn read_stdin_message() -> Result<String, Box<dyn std::error::Error>> {
-    let mut stdin = io::stdin();
-    let mut length_bytes = [0u8; 4];
-    stdin.read_exact(&mut length_bytes)?;
-    let length = u32::from_ne_bytes(length_bytes) as usize;
+// Starts the app in the tray, fully detached so it outlives the host (and the browser
+// closing the host's process group or job) and never writes to the host's stdout
+fn launch_app() -> Result<(), String> {
+    let launch = load_launch_command()
+        .ok_or_else(|| "NeoDLP has not been started yet on this system".to_string())?;
 
-    let mut buffer = vec![0u8; length];
-    stdin.read_exact(&mut buffer)?;
+    let mut command = Command::new(&launch.program);
+    command
+        .args(&launch.args)
+        .arg("--hidden")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x00000008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        const CREATE_BREAKAWAY_FROM_JOB: u32 = 0x01000000;
+        command.creation_flags(
+            DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP | CREATE_BREAKAWAY_FROM_JOB,
+        );
+    }
 
-    let message = String::from_utf8(buffer)?;
-    Ok(message)
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start {}: {}", launch.program, e))
 }
 
-fn write_stdout_message(message: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let message_bytes = message.as_bytes();
-    let message_size = message_bytes.len();
-    io::stdout().write_all(&(message_size as u32).to_ne_bytes())?;
-    io::stdout().write_all(message_bytes)?;
-    io::stdout().flush()?;
-    Ok(())
+// Polls until the app started by `launch_app` accepts the handshake, returns the last
+// attempt after `APP_START_TIMEOUT`
+async fn wait_for_app() -> Result<(WsStream, Response), Option<Response>> {
+    let started = Instant::now();
+    loop {
+        sleep(APP_START_POLL_INTERVAL).await;
+        let connection = connect_any_port().await;
+        if connection.is_ok() || started.elapsed() >= APP_START_TIMEOUT {
+            return connection;
+        }
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    eprintln!("Waiting for message from extension...");
+// Connects to the running app, or starts it and waits for its WebSocket server
+async fn connect_or_launch() -> Result<(WsStream, Response), Response> {
+    match connect_any_port().await {
+        Ok(connection) => return Ok(connection),
+        // A server answered, launching the (single-instance) app would only show its window
+        Err(Some(response)) => return Err(response),
+        Err(None) => {}
+    }
 
-    let input = match read_stdin_message() {
-        Ok(msg) => {
-            eprintln!("Received message: {}", msg);
-            msg
-        }
+    eprintln!("Tauri app is not reachable, launching it...");
+    let connection = match launch_app() {
+        Ok(()) => wait_for_app().await,
         Err(e) => {
-            eprintln!("Error reading message: {:?}", e);
-            return Err(e);
+            eprintln!("{}", e);
+            // Maybe the app is just starting (or restarting its server), give it a moment
+            sleep(Duration::from_secs(2)).await;
+            connect_any_port().await
         }
     };
+    connection.map_err(|rejection| {
+        rejection.unwrap_or_else(|| {
+            eprintln!("Tauri app is still not reachable");
+            Response::error(ErrorCode::Internal, "Failed to connect to Tauri app")
+        })
+    })
+}
+
+fn write_stdout_message(message: &str) -> Result<(), FrameError> {
+    write_message(&mut io::stdout().lock(), message)
+}
+
+// Writes a frame from the app, or an error in its place if it is over the browser's limit
+fn write_app_frame(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match write_stdout_message(text) {
+        Err(FrameError::TooLarge(size)) => {
+            eprintln!("Dropped a {} bytes message from Tauri app", size);
+            let original = serde_json::from_str::<serde_json::Value>(text).ok();
+            let field = |name: &str| original.as_ref().and_then(|value| value.get(name).cloned());
+            let frame = ResponseFrame::new(
+                field("request_id").and_then(|request_id| request_id.as_u64()),
+                Response::error(
+                    ErrorCode::MessageTooLarge,
+                    format!(
+                        "Response of {} bytes is over the native messaging limit",
+                        size
+                    ),
+                ),
+            )
+            .with_id(field("id"));
+            Ok(write_frame(&frame)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+// Reads the next text frame from the app, skipping pings and other control frames
+async fn receive_text(ws_stream: &mut WsStream) -> Option<String> {
+    while let Some(Ok(msg)) = ws_stream.next().await {
+        if let Message::Text(text) = msg {
+            return Some(text.to_string());
+        }
+    }
+    None
+}
+
+fn write_error_message(
+    message: &str,
+    code: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(write_stdout_message(
+        &serde_json::json!({
+            "status": "error",
+            "code": code,
+            "message": message
+        })
+        .to_string(),
+    )?)
+}
+
+fn write_error_response(error: ProtocolError) -> Result<(), Box<dyn std::error::Error>> {
+    let code = serde_json::to_value(error.code)?;
+    write_error_message(&error.message, code.as_str())
+}
+
+fn write_frame(frame: &ResponseFrame) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(write_stdout_message(&frame.to_json())?)
+}
 
+// Reads stdin on its own thread. Messages that could not be read are passed on as errors
+// to reply to, the channel closes when the browser closes the port.
+fn spawn_stdin_reader() -> mpsc::UnboundedReceiver<Result<String, ProtocolError>> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            let message = match read_message(&mut stdin) {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => {
+                    eprintln!("Extension closed stdin");
+                    break;
+                }
+                Err(FrameError::TooLarge(size)) => Err(ProtocolError::new(
+                    ErrorCode::MessageTooLarge,
+                    format!("Message of {} bytes is over the host's limit", size),
+                )),
+                Err(FrameError::InvalidUtf8) => Err(ProtocolError::new(
+                    ErrorCode::InvalidMessage,
+                    "Message is not valid UTF-8",
+                )),
+                Err(FrameError::Io(e)) => {
+                    eprintln!("Error reading message: {}", e);
+                    break;
+                }
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// Authenticates with the `hello` handshake. Returns the app's `hello` response, or the
+// error response to report to the extension.
+async fn authenticate(ws_stream: &mut WsStream) -> Result<Response, Response> {
+    let hello = Request::Hello {
+        version: PROTOCOL_VERSION,
+        token: load_token(),
+    };
+    let hello = serde_json::to_string(&hello)
+        .map_err(|e| Response::error(ErrorCode::Internal, e.to_string()))?;
+    ws_stream
+        .send(Message::Text(hello.into()))
+        .await
+        .map_err(|e| Response::error(ErrorCode::Internal, e.to_string()))?;
+
+    let handshake = receive_text(ws_stream)
+        .await
+        .and_then(|text| serde_json::from_str::<ResponseFrame>(&text).ok());
+    match handshake.map(|frame| frame.response) {
+        Some(response @ Response::Hello { .. }) => Ok(response),
+        Some(response @ Response::Error { .. }) => Err(response),
+        _ => Err(Response::error(
+            ErrorCode::Internal,
+            "Unexpected handshake response from Tauri app",
+        )),
+    }
+}
+
+/// Connects to the app and authenticates with the `hello` handshake. Returns the
+/// app's `hello` response, or the error response to report to the extension.
+async fn connect_to_app() -> Result<(WsStream, Response), Response> {
+    let (ws_stream, hello) = connect_or_launch().await?;
+    if let Response::Hello {
+        version,
+        app_version,
+    } = &hello
+    {
+        eprintln!(
+            "Connected to NeoDLP {} (protocol version {})",
+            app_version, version
+        );
+    }
+    Ok((ws_stream, hello))
+}
+
+// One-shot mode (`runtime.sendNativeMessage`): relays a single request and its response
+async fn run_once(input: &str) -> Result<(), Box<dyn std::error::Error>> {
     // Send immediate response to the extension
     write_stdout_message(
         &serde_json::json!({
@@ -88,42 +284,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .to_string(),
     )?;
 
-    let parsed: Value = serde_json::from_str(&input)?;
-
-    let websocket_url = get_websocket_url();
-    eprintln!("Attempting to connect to {}", websocket_url);
-
-    let mut ws_stream = match connect_with_retry(&websocket_url, 2).await {
-        Ok(stream) => stream,
+    // Reject invalid messages here, without waking up the app
+    let frame = match parse_request(input) {
+        Ok(frame) => frame,
         Err(e) => {
-            eprintln!("Failed to connect after multiple attempts: {:?}", e);
-            write_stdout_message(
-                &serde_json::json!({
-                    "status": "error",
-                    "message": "Failed to connect to Tauri app"
-                })
-                .to_string(),
-            )?;
-            return Err(e);
+            eprintln!("Invalid message from extension: {}", e);
+            return write_error_response(e);
+        }
+    };
+
+    let mut ws_stream = match connect_to_app().await {
+        Ok((ws_stream, _)) => ws_stream,
+        Err(response) => {
+            if let Response::Error { code, message } = response {
+                eprintln!("Handshake failed: {}", message);
+                let code = serde_json::to_value(code)?;
+                write_error_message(&message, code.as_str())?;
+            }
+            return Ok(());
         }
     };
 
     // Send message to Tauri app
     ws_stream
-        .send(Message::Text(parsed.to_string().into()))
+        .send(Message::Text(serde_json::to_string(&frame)?.into()))
         .await?;
 
     // Receive response from Tauri app
-    if let Some(Ok(msg)) = ws_stream.next().await {
+    if let Some(text) = receive_text(&mut ws_stream).await {
         // Send Tauri app's response back to browser extension
-        if let Message::Text(text) = msg {
-            write_stdout_message(
-                &serde_json::json!({
-                    "status": "success",
-                    "response": text.to_string()
-                })
-                .to_string(),
-            )?;
+        let envelope = serde_json::json!({
+            "status": "success",
+            "response": text
+        });
+        if let Err(FrameError::TooLarge(size)) = write_stdout_message(&envelope.to_string()) {
+            eprintln!("Dropped a {} bytes response from Tauri app", size);
+            write_error_response(ProtocolError::new(
+                ErrorCode::MessageTooLarge,
+                format!(
+                    "Response of {} bytes is over the native messaging limit",
+                    size
+                ),
+            ))?;
         }
     }
 
@@ -132,3 +334,104 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+// Persistent mode (`runtime.connectNative`): the port starts with a `hello`, then every
+// request is forwarded over one WebSocket and every frame from the app (responses and
+// events alike) is written back as is, until either side closes
+async fn run_persistent(
+    hello: RequestFrame,
+    mut messages: mpsc::UnboundedReceiver<Result<String, ProtocolError>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Request::Hello { version, .. } = hello.request {
+        if !is_compatible(version) {
+            let response = Response::error(
+                ErrorCode::UnsupportedVersion,
+                format!(
+                    "Protocol version {} is not supported, the host speaks version {}",
+                    version, PROTOCOL_VERSION
+                ),
+            );
+            return write_frame(&ResponseFrame::new(None, response).with_id(hello.id));
+        }
+    }
+
+    let mut ws_stream = match connect_to_app().await {
+        Ok((ws_stream, response)) => {
+            write_frame(&ResponseFrame::new(None, response).with_id(hello.id))?;
+            ws_stream
+        }
+        Err(response) => {
+            return write_frame(&ResponseFrame::new(None, response).with_id(hello.id));
+        }
+    };
+
+    loop {
+        tokio::select! {
+            message = messages.recv() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        eprintln!("Unreadable message from extension: {}", e);
+                        write_frame(&ResponseFrame::new(None, e.into()))?;
+                        continue;
+                    }
+                    None => {
+                        eprintln!("Extension closed the port");
+                        break;
+                    }
+                };
+                match parse_request(&message) {
+                    Ok(frame) => {
+                        ws_stream
+                            .send(Message::Text(serde_json::to_string(&frame)?.into()))
+                            .await?;
+                    }
+                    Err(e) => {
+                        eprintln!("Invalid message from extension: {}", e);
+                        let frame = ResponseFrame::new(None, e.into())
+                            .with_id(parse_request_id(&message));
+                        write_frame(&frame)?;
+                    }
+                }
+            }
+            message = ws_stream.next() => match message {
+                Some(Ok(Message::Text(text))) => write_app_frame(&text)?,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    eprintln!("Tauri app closed the connection");
+                    let response =
+                        Response::error(ErrorCode::Internal, "Connection to Tauri app was closed");
+                    write_frame(&ResponseFrame::new(None, response))?;
+                    return Ok(());
+                }
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+
+    ws_stream.close(None).await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("Waiting for message from extension...");
+
+    let mut messages = spawn_stdin_reader();
+    // A browser that closes stdin right away is not an error, just nothing to do
+    let input = match messages.recv().await {
+        Some(Ok(input)) => input,
+        Some(Err(e)) => {
+            eprintln!("Unreadable message from extension: {}", e);
+            return write_error_response(e);
+        }
+        None => return Ok(()),
+    };
+    eprintln!("Received message: {}", input);
+
+    match parse_request(&input) {
+        Ok(frame) if matches!(frame.request, Request::Hello { .. }) => {
+            run_persistent(frame, messages).await
+        }
+        _ => run_once(&input).await,
+    }
+}
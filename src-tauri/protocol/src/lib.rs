@@ -0,0 +1,507 @@
+//! Messages exchanged between the NeoDLP browser extension (through `neodlp-msghost`)
+//! and the NeoDLP app over the local WebSocket.
+//!
+//! Requests are JSON objects tagged by `command`, responses are tagged by `type`
+//! and carry the `request_id` assigned by the app to the request they answer.
+//! Clients that `subscribe` also receive `event` frames (without `request_id`)
+//! as their downloads progress. Requests may carry an `id` of the client's choice,
+//! it is copied into the response so clients can match overlapping requests.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version spoken by this build, exchanged in the `hello` handshake.
+/// Bump it on any breaking change to the messages below.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// Every command of `Request`, used to tell unknown commands apart from malformed ones
+const COMMANDS: &[&str] = &[
+    "hello",
+    "ping",
+    "download",
+    "enqueue",
+    "fetch_info",
+    "status",
+    "cancel",
+    "subscribe",
+    "unsubscribe",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Handshake, must be the first message of a connection. `token` is the
+    /// shared secret the app stores next to `msghost-config.json`.
+    Hello {
+        version: u32,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    Ping,
+    /// Opens the URL in the app so the user can pick what to download
+    Download {
+        url: String,
+        #[serde(default)]
+        argument: Option<String>,
+    },
+    /// Adds the URL straight to the download queue
+    Enqueue {
+        url: String,
+        #[serde(default)]
+        format: Option<String>,
+    },
+    /// Returns the yt-dlp metadata of the URL
+    FetchInfo {
+        url: String,
+    },
+    /// Returns one download, or all unfinished downloads when no ID is given
+    Status {
+        #[serde(default)]
+        download_id: Option<String>,
+    },
+    Cancel {
+        download_id: String,
+    },
+    /// Starts pushing events about one download, or about all of them when no ID is given
+    Subscribe {
+        #[serde(default)]
+        download_id: Option<String>,
+    },
+    /// Stops all events
+    Unsubscribe,
+}
+
+/// A request as sent over the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+impl Request {
+    pub fn command(&self) -> &'static str {
+        match self {
+            Request::Hello { .. } => "hello",
+            Request::Ping => "ping",
+            Request::Download { .. } => "download",
+            Request::Enqueue { .. } => "enqueue",
+            Request::FetchInfo { .. } => "fetch_info",
+            Request::Status { .. } => "status",
+            Request::Cancel { .. } => "cancel",
+            Request::Subscribe { .. } => "subscribe",
+            Request::Unsubscribe => "unsubscribe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    UnknownCommand,
+    UnsupportedVersion,
+    Unauthorized,
+    MessageTooLarge,
+    NotFound,
+    Timeout,
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// A download as reported by the `status` command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadSummary {
+    pub download_id: String,
+    pub status: String,
+    pub progress: Option<f64>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Pushed to subscribed clients, from the `downloads` table as the app updates it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    Queued {
+        download: DownloadSummary,
+    },
+    Progress {
+        download_id: String,
+        progress: Option<f64>,
+        /// Bytes per second
+        speed: Option<f64>,
+        /// Seconds
+        eta: Option<i64>,
+    },
+    Finished {
+        download_id: String,
+        filepath: Option<String>,
+    },
+    Failed {
+        download_id: String,
+        message: String,
+    },
+}
+
+impl DownloadEvent {
+    pub fn download_id(&self) -> &str {
+        match self {
+            DownloadEvent::Queued { download } => &download.download_id,
+            DownloadEvent::Progress { download_id, .. }
+            | DownloadEvent::Finished { download_id, .. }
+            | DownloadEvent::Failed { download_id, .. } => download_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Hello {
+        version: u32,
+        app_version: String,
+    },
+    Pong,
+    /// The request was handed over to the app (`download`, `enqueue`)
+    Accepted,
+    Info {
+        info: serde_json::Value,
+    },
+    Status {
+        downloads: Vec<DownloadSummary>,
+    },
+    Cancelled {
+        download_id: String,
+    },
+    Subscribed,
+    Unsubscribed,
+    /// Not an answer to a request, see `DownloadEvent`
+    Event {
+        #[serde(flatten)]
+        event: DownloadEvent,
+    },
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+impl Response {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Response::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ProtocolError> for Response {
+    fn from(error: ProtocolError) -> Self {
+        Response::Error {
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// A response as sent over the wire, `request_id` is missing for events and for
+/// errors about messages that could not be parsed into a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+    /// The `id` of the request, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+impl ResponseFrame {
+    pub fn new(request_id: Option<u64>, response: Response) -> Self {
+        Self {
+            request_id,
+            id: None,
+            response,
+        }
+    }
+
+    pub fn with_id(mut self, id: Option<serde_json::Value>) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|e| {
+            format!(
+                r#"{{"type":"error","code":"internal","message":"Failed to serialize response: {}"}}"#,
+                e
+            )
+        })
+    }
+}
+
+pub fn is_compatible(version: u32) -> bool {
+    version == PROTOCOL_VERSION
+}
+
+/// Parses a request, with distinct errors for invalid JSON, unknown commands
+/// and known commands with missing or invalid fields.
+pub fn parse_request(text: &str) -> Result<RequestFrame, ProtocolError> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| {
+        ProtocolError::new(ErrorCode::InvalidMessage, format!("Invalid JSON: {}", e))
+    })?;
+
+    let command = value
+        .get("command")
+        .and_then(|command| command.as_str())
+        .ok_or_else(|| {
+            ProtocolError::new(ErrorCode::InvalidMessage, "Missing \"command\" field")
+        })?;
+    if !COMMANDS.contains(&command) {
+        return Err(ProtocolError::new(
+            ErrorCode::UnknownCommand,
+            format!("Unknown command: {}", command),
+        ));
+    }
+
+    let command = command.to_string();
+    serde_json::from_value(value).map_err(|e| {
+        ProtocolError::new(
+            ErrorCode::InvalidMessage,
+            format!("Invalid \"{}\" request: {}", command, e),
+        )
+    })
+}
+
+/// The `id` of a message `parse_request` rejected, if it can still be read,
+/// so the error reply can be matched with the request.
+pub fn parse_request_id(text: &str) -> Option<serde_json::Value> {
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()?
+        .get("id")
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn all_requests() -> Vec<Request> {
+        vec![
+            Request::Hello {
+                version: PROTOCOL_VERSION,
+                token: Some("secret".to_string()),
+            },
+            Request::Ping,
+            Request::Download {
+                url: "https://example.com/watch".to_string(),
+                argument: Some("-f best".to_string()),
+            },
+            Request::Enqueue {
+                url: "https://example.com/watch".to_string(),
+                format: None,
+            },
+            Request::FetchInfo {
+                url: "https://example.com/watch".to_string(),
+            },
+            Request::Status { download_id: None },
+            Request::Cancel {
+                download_id: "d1".to_string(),
+            },
+            Request::Subscribe {
+                download_id: Some("d1".to_string()),
+            },
+            Request::Unsubscribe,
+        ]
+    }
+
+    fn summary() -> DownloadSummary {
+        DownloadSummary {
+            download_id: "d1".to_string(),
+            status: "downloading".to_string(),
+            progress: Some(42.5),
+            title: Some("Title".to_string()),
+            url: None,
+        }
+    }
+
+    fn all_events() -> Vec<DownloadEvent> {
+        vec![
+            DownloadEvent::Queued {
+                download: summary(),
+            },
+            DownloadEvent::Progress {
+                download_id: "d1".to_string(),
+                progress: Some(10.0),
+                speed: Some(1024.0),
+                eta: Some(30),
+            },
+            DownloadEvent::Finished {
+                download_id: "d1".to_string(),
+                filepath: Some("/tmp/video.mp4".to_string()),
+            },
+            DownloadEvent::Failed {
+                download_id: "d1".to_string(),
+                message: "yt-dlp exited with 1".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn every_request_round_trips_and_is_a_known_command() {
+        let requests = all_requests();
+        assert_eq!(requests.len(), COMMANDS.len());
+        for request in requests {
+            let frame = RequestFrame {
+                id: Some(json!(7)),
+                request,
+            };
+            let text = serde_json::to_string(&frame).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(value["command"], frame.request.command());
+            assert!(COMMANDS.contains(&frame.request.command()));
+            assert_eq!(parse_request(&text).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn requests_use_the_wire_format() {
+        let frame =
+            parse_request(r#"{"id": "a", "command": "enqueue", "url": "https://x"}"#).unwrap();
+        assert_eq!(frame.id, Some(json!("a")));
+        assert_eq!(
+            frame.request,
+            Request::Enqueue {
+                url: "https://x".to_string(),
+                format: None,
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(RequestFrame {
+                id: None,
+                request: Request::Status { download_id: None },
+            })
+            .unwrap(),
+            json!({"command": "status", "download_id": null})
+        );
+    }
+
+    #[test]
+    fn every_response_round_trips() {
+        let mut responses = vec![
+            Response::Hello {
+                version: PROTOCOL_VERSION,
+                app_version: "1.0.0".to_string(),
+            },
+            Response::Pong,
+            Response::Accepted,
+            Response::Info {
+                info: json!({"title": "Title"}),
+            },
+            Response::Status {
+                downloads: vec![summary()],
+            },
+            Response::Cancelled {
+                download_id: "d1".to_string(),
+            },
+            Response::Subscribed,
+            Response::Unsubscribed,
+            Response::error(ErrorCode::NotFound, "No download d1"),
+        ];
+        responses.extend(
+            all_events()
+                .into_iter()
+                .map(|event| Response::Event { event }),
+        );
+
+        for response in responses {
+            let frame = ResponseFrame::new(Some(3), response).with_id(Some(json!("a")));
+            let parsed: ResponseFrame = serde_json::from_str(&frame.to_json()).unwrap();
+            assert_eq!(parsed, frame);
+        }
+    }
+
+    #[test]
+    fn responses_and_events_use_the_wire_format() {
+        assert_eq!(
+            serde_json::to_value(ResponseFrame::new(Some(3), Response::Pong)).unwrap(),
+            json!({"request_id": 3, "type": "pong"})
+        );
+        assert_eq!(
+            serde_json::to_value(ResponseFrame::new(
+                None,
+                ProtocolError::new(ErrorCode::UnknownCommand, "Unknown command: jump").into(),
+            ))
+            .unwrap(),
+            json!({"type": "error", "code": "unknown_command", "message": "Unknown command: jump"})
+        );
+        let event = DownloadEvent::Finished {
+            download_id: "d1".to_string(),
+            filepath: None,
+        };
+        assert_eq!(event.download_id(), "d1");
+        assert_eq!(
+            serde_json::to_value(ResponseFrame::new(None, Response::Event { event })).unwrap(),
+            json!({"type": "event", "event": "finished", "download_id": "d1", "filepath": null})
+        );
+    }
+
+    #[test]
+    fn unknown_commands_are_told_apart_from_invalid_messages() {
+        let error = parse_request(r#"{"id": 1, "command": "jump"}"#).unwrap_err();
+        assert_eq!(error.code, ErrorCode::UnknownCommand);
+
+        for text in [
+            r#"{"command": "ping""#,
+            r#"{"id": 1}"#,
+            r#"{"command": 5}"#,
+            r#"{"command": "cancel"}"#,
+            r#"{"command": "hello", "version": "2"}"#,
+        ] {
+            let error = parse_request(text).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidMessage, "{}", text);
+        }
+    }
+
+    #[test]
+    fn the_id_of_a_rejected_request_can_still_be_read() {
+        assert_eq!(
+            parse_request_id(r#"{"id": "abc", "command": "jump"}"#),
+            Some(json!("abc"))
+        );
+        assert_eq!(parse_request_id(r#"{"command": "cancel"}"#), None);
+        assert_eq!(parse_request_id("not json"), None);
+    }
+
+    #[test]
+    fn only_the_current_version_is_compatible() {
+        assert!(is_compatible(PROTOCOL_VERSION));
+        assert!(!is_compatible(PROTOCOL_VERSION + 1));
+    }
+}
@@ -1,51 +1,199 @@
-use directories::ProjectDirs;
-use serde::{Deserialize, Serialize};
+use neodlp_config::{LaunchCommand, LAUNCH_FILE, PORT_FILE, TOKEN_FILE};
+use serde::Serialize;
+use serde_json::{json, Value};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Config {
-    pub port: u16,
+pub use neodlp_config::{
+    apply_patch, file_version, get_config_dir, get_config_path, get_config_path_source,
+    get_port_override, get_port_source, load_config_checked, parse_config, save_config, Config,
+    DownloadConfig, ValueSource, PORT_ENV,
+};
+
+// Frontend settings keys that are also kept in the config, with their place in it
+const CONFIG_SETTINGS: &[(&str, &str)] = &[
+    ("download_dir", "download.download_dir"),
+    ("max_parallel_downloads", "download.max_parallel_downloads"),
+    ("max_retries", "download.max_retries"),
+    ("auto_resume_downloads", "download.auto_resume_downloads"),
+    ("filename_template", "download.filename_template"),
+    ("shutdown_timeout", "download.shutdown_timeout"),
+    ("use_proxy", "network.use_proxy"),
+    ("proxy_url", "network.proxy_url"),
+    ("use_rate_limit", "network.use_rate_limit"),
+    ("rate_limit", "network.rate_limit"),
+    (
+        "use_force_internet_protocol",
+        "network.use_force_internet_protocol",
+    ),
+    ("force_internet_protocol", "network.force_internet_protocol"),
+    ("websocket_port", "extension.port"),
+    ("websocket_port_fallback", "extension.port_fallback"),
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EffectiveValue<T> {
+    pub value: T,
+    pub source: ValueSource,
+}
+
+/// Values actually in use where they can be overridden from outside the config file.
+#[derive(Debug, Serialize, Clone)]
+pub struct EffectiveSettings {
+    pub port: EffectiveValue<u16>,
+    pub config_path: EffectiveValue<Option<String>>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self { port: 53511 }
+/// The config as saved, plus what overrides it for this run.
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigInfo {
+    #[serde(flatten)]
+    pub config: Config,
+    pub effective: EffectiveSettings,
+}
+
+impl ConfigInfo {
+    pub fn new(config: Config, fallback_port: Option<u16>) -> Self {
+        let port = match fallback_port {
+            Some(port) => EffectiveValue {
+                value: port,
+                source: ValueSource::Fallback,
+            },
+            None => EffectiveValue {
+                value: config.effective_port(),
+                source: get_port_source(),
+            },
+        };
+        let effective = EffectiveSettings {
+            port,
+            config_path: EffectiveValue {
+                value: get_config_path().map(|path| path.to_string_lossy().into_owned()),
+                source: get_config_path_source(),
+            },
+        };
+        Self { config, effective }
     }
 }
 
-pub fn get_config_dir() -> Option<PathBuf> {
-    ProjectDirs::from("com", "neosubhamoy", "neodlp")
-        .map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+/// Config patch for a frontend setting, `None` if the setting isn't part of the config.
+pub fn setting_patch(key: &str, value: &Value) -> Option<Value> {
+    CONFIG_SETTINGS
+        .iter()
+        .find(|(setting, _)| *setting == key)
+        .map(|(_, field)| {
+            field
+                .rsplit('.')
+                .fold(value.clone(), |patch, name| json!({ name: patch }))
+        })
+}
+
+/// Frontend settings whose value differs between two configs, with their new value.
+pub fn changed_settings(old: &Config, new: &Config) -> Vec<(&'static str, Value)> {
+    let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+    CONFIG_SETTINGS
+        .iter()
+        .filter_map(|(key, field)| {
+            let pointer = format!("/{}", field.replace('.', "/"));
+            let value = new.pointer(&pointer)?;
+            (old.pointer(&pointer) != Some(value)).then(|| (*key, value.clone()))
+        })
+        .collect()
 }
 
-pub fn get_config_path() -> Option<PathBuf> {
-    get_config_dir().map(|dir| dir.join("msghost-config.json"))
+/// Whether the config file is missing or predates the download and network
+/// sections, their values then only exist in the frontend's settings table.
+pub fn needs_settings_import() -> bool {
+    let Some(content) = get_config_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return true;
+    };
+    serde_json::from_str::<Value>(&content).is_ok_and(|value| file_version(&value) < 2)
 }
 
-pub fn load_config() -> Config {
-    if let Some(config_path) = get_config_path() {
-        if let Ok(content) = fs::read_to_string(config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
-            }
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Returns the token shared with `neodlp-msghost`, generating it on first run.
+/// The file is only readable by the current user.
+pub fn load_or_create_token() -> Result<String, String> {
+    let config_dir =
+        get_config_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
+    let token_path = config_dir.join(TOKEN_FILE);
+
+    if let Ok(token) = fs::read_to_string(&token_path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
         }
     }
-    Config::default()
+
+    fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let token = generate_token()?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&token_path)
+        .map_err(|e| format!("Failed to create token file: {}", e))?;
+    file.write_all(token.as_bytes())
+        .map_err(|e| format!("Failed to write token file: {}", e))?;
+
+    Ok(token)
+}
+
+fn get_launch_command() -> Result<LaunchCommand, String> {
+    // Inside a Flatpak or an AppImage the executable path is only valid while the app runs
+    if let Ok(flatpak_id) = std::env::var("FLATPAK_ID") {
+        return Ok(LaunchCommand {
+            program: "flatpak".to_string(),
+            args: vec!["run".to_string(), flatpak_id],
+        });
+    }
+    if let Ok(appimage) = std::env::var("APPIMAGE") {
+        return Ok(LaunchCommand {
+            program: appimage,
+            args: Vec::new(),
+        });
+    }
+    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    Ok(LaunchCommand {
+        program: exe_path.to_string_lossy().into_owned(),
+        args: Vec::new(),
+    })
 }
 
-pub fn save_config(config: &Config) -> Result<(), String> {
+/// Records how to start the app, so `neodlp-msghost` can launch it on demand.
+pub fn save_launch_command() -> Result<(), String> {
     let config_dir =
         get_config_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
 
     fs::create_dir_all(&config_dir)
         .map_err(|e| format!("Failed to create config directory: {}", e))?;
 
-    let config_path = config_dir.join("msghost-config.json");
-    let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    let content = serde_json::to_string_pretty(&get_launch_command()?)
+        .map_err(|e| format!("Failed to serialize launch command: {}", e))?;
 
-    fs::write(config_path, content).map_err(|e| format!("Failed to write config file: {}", e))?;
+    fs::write(config_dir.join(LAUNCH_FILE), content)
+        .map_err(|e| format!("Failed to write launch command: {}", e))
+}
+
+pub fn save_active_port(port: u16) -> Result<(), String> {
+    let config_dir =
+        get_config_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
+
+    fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
 
-    Ok(())
+    fs::write(config_dir.join(PORT_FILE), port.to_string())
+        .map_err(|e| format!("Failed to write active port: {}", e))
 }
@@ -0,0 +1,130 @@
+use crate::{
+    config::{changed_settings, get_config_path, parse_config, Config},
+    database::get_pool,
+    repository::save_setting,
+    start_websocket_server, WebSocketState, WEBSOCKET_LOG_TARGET,
+};
+use log::{error, info, warn};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{fs, path::Path, sync::Arc, time::Duration};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::timeout,
+};
+
+// Editors write a file in several steps, reload once they have been quiet this long
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Saves usually replace the file (write a temp file, then rename), so the whole
+// directory is watched and only events touching the config file are kept
+fn is_config_event(event: &Event, config_path: &Path) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) && event
+        .paths
+        .iter()
+        .any(|path| path.file_name() == config_path.file_name())
+}
+
+async fn reload_config(app_handle: &AppHandle, config_path: &Path) -> Result<(), String> {
+    // Read before taking the lock, a patch saved meanwhile writes the file again
+    // and the event it causes brings its content back
+    let content = tokio::fs::read_to_string(config_path)
+        .await
+        .map_err(|e| format!("Failed to read config file: {}", e))?;
+    let (old_config, config) = {
+        let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+        let mut state = state.lock().await;
+        let config = match parse_config(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                state.config_load_error = Some(e.clone());
+                return Err(e);
+            }
+        };
+        state.config_load_error = None;
+        // Also the case after every save made by the app itself
+        if config == state.config {
+            return Ok(());
+        }
+        (std::mem::replace(&mut state.config, config.clone()), config)
+    };
+    info!("Config file changed, applying it");
+
+    // The port and the fallback range both decide where the server listens
+    if config.extension != old_config.extension {
+        if let Err(e) = start_websocket_server(app_handle.clone(), config.effective_port()).await {
+            error!(target: WEBSOCKET_LOG_TARGET, "Failed to restart WebSocket server: {}", e);
+        }
+    }
+
+    save_changed_settings(app_handle, &old_config, &config).await;
+    let _ = app_handle.emit("config-changed", &config);
+    Ok(())
+}
+
+// Keeps the frontend's copy of the settings in line with a config edited outside the app
+async fn save_changed_settings(app_handle: &AppHandle, old_config: &Config, config: &Config) {
+    if let Ok(pool) = get_pool(app_handle) {
+        for (key, value) in changed_settings(old_config, config) {
+            if let Err(e) = save_setting(&pool, key, &value).await {
+                warn!("Failed to update setting {} from the config: {}", key, e);
+            }
+        }
+    }
+}
+
+/// Applies edits made to the config file by users or provisioning scripts while
+/// the app runs. Invalid files are ignored, reported by `get_config_load_error`
+/// and the `config-load-failed` event.
+pub async fn watch_config(app_handle: AppHandle) {
+    let Some(config_path) = get_config_path() else {
+        return;
+    };
+    let Some(config_dir) = config_path.parent() else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(config_dir) {
+        error!("Failed to create {}: {}", config_dir.display(), e);
+        return;
+    }
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let _ = sender.send(event);
+    });
+    // Dropping the watcher stops it, it has to live as long as the loop
+    let _watcher = match watcher.and_then(|mut watcher| {
+        watcher.watch(config_dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("Failed to watch the config file: {}", e);
+            return;
+        }
+    };
+
+    while let Some(event) = receiver.recv().await {
+        match event {
+            Ok(event) if is_config_event(&event, &config_path) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Config file watcher error: {}", e);
+                continue;
+            }
+        }
+        while let Ok(Some(_)) = timeout(DEBOUNCE, receiver.recv()).await {}
+
+        // A deleted file keeps the current config until it is saved again
+        if !config_path.is_file() {
+            continue;
+        }
+        if let Err(e) = reload_config(&app_handle, &config_path).await {
+            warn!("Ignoring config file change: {}", e);
+            let _ = app_handle.emit("config-load-failed", &e);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use crate::migrations::get_migrations;
+use log::{error, info, warn};
+use serde::Serialize;
+use sqlx::{
+    migrate::{Migration, MigrationType, Migrator},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool},
+    Pool, Row, SqlSafeStr, Sqlite,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_sql::MigrationKind;
+
+// Same name the frontend passes to `Database.load('sqlite:database.db')`
+pub const DATABASE_FILE: &str = "database.db";
+// How long a connection waits for a lock held by the frontend's (plugin-sql) connection
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+// Directory (next to the database) holding the pre-migration backups
+const BACKUPS_DIR: &str = "database-backups";
+// Holds the name of a backup to restore on the next launch, before the database is opened
+const PENDING_RESTORE_FILE: &str = "restore-pending";
+// Older backups are deleted once there are more than this many
+const MAX_BACKUPS: usize = 10;
+
+/// The single SQLite pool used by every Rust command, created (and migrated)
+/// in `setup` before the window loads. Holds the error if that failed.
+pub struct Database {
+    pool: Result<Pool<Sqlite>, String>,
+}
+
+impl Database {
+    pub fn pool(&self) -> Result<Pool<Sqlite>, String> {
+        self.pool
+            .clone()
+            .map_err(|e| format!("Database is not ready: {}", e))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseBackup {
+    name: String,
+    size: u64,
+}
+
+pub fn get_pool(app_handle: &AppHandle) -> Result<Pool<Sqlite>, String> {
+    app_handle
+        .try_state::<Database>()
+        .ok_or_else(|| "Database is not ready: not initialized yet".to_string())?
+        .pool()
+}
+
+/// Path of the database file, the same one `tauri_plugin_sql` resolves
+/// `sqlite:database.db` to (it is relative to the app config dir).
+pub fn get_database_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .map(|dir| dir.join(DATABASE_FILE))
+        .map_err(|e| format!("Failed to get app config dir: {}", e))
+}
+
+fn get_backups_dir(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(BACKUPS_DIR)
+}
+
+pub(crate) fn get_migrator() -> Migrator {
+    Migrator::with_migrations(
+        get_migrations()
+            .into_iter()
+            .map(|migration| {
+                let migration_type = match migration.kind {
+                    MigrationKind::Up => MigrationType::ReversibleUp,
+                    MigrationKind::Down => MigrationType::ReversibleDown,
+                };
+                // Matches how plugin-sql recorded the migrations (same checksums),
+                // so databases migrated by older versions are picked up as is
+                Migration::new(
+                    migration.version,
+                    migration.description.into(),
+                    migration_type,
+                    migration.sql.into_sql_str(),
+                    false,
+                )
+            })
+            .collect(),
+    )
+}
+
+// Versions of the Up migrations that have not been applied yet (all of them on a new database)
+async fn get_pending_migrations(pool: &Pool<Sqlite>, migrator: &Migrator) -> Vec<i64> {
+    let applied: Vec<i64> = sqlx::query("SELECT version FROM _sqlx_migrations WHERE success = 1")
+        .fetch_all(pool)
+        .await
+        .map(|rows| rows.iter().map(|row| row.get("version")).collect())
+        .unwrap_or_default();
+
+    migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect()
+}
+
+async fn has_user_tables(pool: &Pool<Sqlite>) -> bool {
+    sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'downloads'")
+        .fetch_optional(pool)
+        .await
+        .map(|row| row.is_some())
+        .unwrap_or(false)
+}
+
+fn prune_backups(backups_dir: &Path) {
+    let mut backups = list_backups(backups_dir);
+    if backups.len() <= MAX_BACKUPS {
+        return;
+    }
+    // Names start with the timestamp, so the oldest sort first
+    backups.sort_by(|a, b| a.name.cmp(&b.name));
+    for backup in &backups[..backups.len() - MAX_BACKUPS] {
+        if let Err(e) = fs::remove_file(backups_dir.join(&backup.name)) {
+            warn!(
+                "Failed to delete old database backup {}: {}",
+                backup.name, e
+            );
+        }
+    }
+}
+
+/// Writes a consistent copy of the database (including any WAL content) to
+/// `database-backups/database-<timestamp>-<label>.db` and returns its path.
+async fn backup_database(
+    pool: &Pool<Sqlite>,
+    db_path: &Path,
+    label: &str,
+) -> Result<PathBuf, String> {
+    let backups_dir = get_backups_dir(db_path);
+    fs::create_dir_all(&backups_dir)
+        .map_err(|e| format!("Failed to create {}: {}", backups_dir.display(), e))?;
+
+    let timestamp: String = sqlx::query("SELECT strftime('%Y%m%d-%H%M%S', 'now') AS timestamp")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .get("timestamp");
+    let backup_path = backups_dir.join(format!("database-{}-{}.db", timestamp, label));
+    if backup_path.exists() {
+        return Ok(backup_path);
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+
+    prune_backups(&backups_dir);
+    Ok(backup_path)
+}
+
+fn list_backups(backups_dir: &Path) -> Vec<DatabaseBackup> {
+    let Ok(entries) = fs::read_dir(backups_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "db"))
+        .filter_map(|entry| {
+            Some(DatabaseBackup {
+                name: entry.file_name().to_str()?.to_string(),
+                size: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect()
+}
+
+// Backup names come from the frontend, only accept plain file names inside the backups dir
+fn resolve_backup(backups_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let is_plain_name = Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name);
+    if !is_plain_name || !name.ends_with(".db") {
+        return Err(format!("Invalid backup name: {}", name));
+    }
+    let backup_path = backups_dir.join(name);
+    if !backup_path.is_file() {
+        return Err(format!("Backup not found: {}", name));
+    }
+    Ok(backup_path)
+}
+
+// Replaces the database file with the backup requested by `restore_database_backup`.
+// Runs before any connection is opened, the current database is backed up first.
+async fn apply_pending_restore(db_path: &Path) -> Result<(), String> {
+    let backups_dir = get_backups_dir(db_path);
+    let marker = backups_dir.join(PENDING_RESTORE_FILE);
+    let Ok(name) = fs::read_to_string(&marker) else {
+        return Ok(());
+    };
+    // Never retry a restore that keeps failing on every launch
+    let _ = fs::remove_file(&marker);
+
+    let backup_path = resolve_backup(&backups_dir, name.trim())?;
+    if db_path.exists() {
+        let options = SqliteConnectOptions::new().filename(db_path);
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+        let safety_backup = backup_database(&pool, db_path, "before-restore").await;
+        pool.close().await;
+        let safety_backup = safety_backup?;
+        info!("Backed up current database to {}", safety_backup.display());
+    }
+
+    // Stale WAL files belong to the old database and must not be replayed on the restored one
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        let _ = fs::remove_file(PathBuf::from(path));
+    }
+    fs::copy(&backup_path, db_path)
+        .map_err(|e| format!("Failed to restore {}: {}", backup_path.display(), e))?;
+    info!("Restored database from {}", backup_path.display());
+    Ok(())
+}
+
+async fn connect(app_handle: &AppHandle) -> Result<Pool<Sqlite>, String> {
+    let db_path = get_database_path(app_handle)?;
+    if let Some(dir) = db_path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    if let Err(e) = apply_pending_restore(&db_path).await {
+        error!("Failed to restore database backup: {}", e);
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(&db_path)
+        .create_if_missing(true)
+        // WAL lets the frontend's connection read while the backend writes progress
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(BUSY_TIMEOUT);
+    let pool = SqlitePool::connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+
+    let migrator = get_migrator();
+    let pending = get_pending_migrations(&pool, &migrator).await;
+    let mut backup_path = None;
+    if !pending.is_empty() && has_user_tables(&pool).await {
+        // The table rebuilding migrations must never cost the download history,
+        // refuse to migrate without a backup to fall back to
+        let label = format!("before-v{}", pending[0]);
+        let path = backup_database(&pool, &db_path, &label).await?;
+        info!(
+            "Backed up database to {} before applying migrations {:?}",
+            path.display(),
+            pending
+        );
+        backup_path = Some(path);
+    }
+
+    if let Err(e) = migrator.run(&pool).await {
+        return Err(match backup_path {
+            Some(path) => format!(
+                "Failed to run migrations: {} (a backup was saved to {})",
+                e,
+                path.display()
+            ),
+            None => format!("Failed to run migrations: {}", e),
+        });
+    }
+
+    info!("Database ready at {}", db_path.display());
+    Ok(pool)
+}
+
+/// Opens the database and applies pending migrations. Blocks until done, so it
+/// must run in `setup` before the webview starts issuing queries.
+pub fn init_database(app_handle: &AppHandle) -> Database {
+    let pool = tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(connect(app_handle))
+    });
+    if let Err(e) = &pool {
+        error!("Database initialization failed: {}", e);
+    }
+    Database { pool }
+}
+
+// Reverts every applied migration newer than `version` with its Down migration
+async fn revert_migrations(pool: &Pool<Sqlite>, version: i64) -> Result<(), String> {
+    let migrator = get_migrator();
+    let latest = migrator
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0);
+    if !(0..=latest).contains(&version) {
+        return Err(format!(
+            "Invalid schema version {}, expected 0 to {}",
+            version, latest
+        ));
+    }
+    migrator
+        .undo(pool, version)
+        .await
+        .map_err(|e| format!("Failed to revert migrations: {}", e))
+}
+
+/// Takes the schema back to `version` for downgrading to an older release, which
+/// refuses to open a database migrated past what it knows. Backs the database up
+/// first, runs from `--rollback-database <version>` while the app is closed.
+pub fn rollback_database(app_handle: &AppHandle, version: i64) -> Result<(), String> {
+    let db_path = get_database_path(app_handle)?;
+    if !db_path.exists() {
+        return Err(format!("No database at {}", db_path.display()));
+    }
+
+    tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(async {
+            let options = SqliteConnectOptions::new()
+                .filename(&db_path)
+                .busy_timeout(BUSY_TIMEOUT);
+            let pool = SqlitePool::connect_with(options)
+                .await
+                .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+
+            let label = format!("before-rollback-v{}", version);
+            let result = match backup_database(&pool, &db_path, &label).await {
+                Ok(path) => {
+                    info!(
+                        "Backed up database to {} before the rollback",
+                        path.display()
+                    );
+                    revert_migrations(&pool, version).await
+                }
+                Err(e) => Err(e),
+            };
+            pool.close().await;
+            result
+        })
+    })?;
+
+    info!("Rolled the database back to schema version {}", version);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_database_backups(app_handle: AppHandle) -> Result<Vec<DatabaseBackup>, String> {
+    let db_path = get_database_path(&app_handle)?;
+    let mut backups = list_backups(&get_backups_dir(&db_path));
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Restores a backup from `list_database_backups`. The database can't be swapped
+/// while it is open, so the restore is applied on the next launch and the app
+/// restarts right away (ongoing downloads are paused as on a normal quit).
+#[tauri::command]
+pub async fn restore_database_backup(app_handle: AppHandle, name: String) -> Result<(), String> {
+    let backups_dir = get_backups_dir(&get_database_path(&app_handle)?);
+    resolve_backup(&backups_dir, &name)?;
+    fs::write(backups_dir.join(PENDING_RESTORE_FILE), &name)
+        .map_err(|e| format!("Failed to schedule the restore: {}", e))?;
+
+    info!("Restoring database backup {} on restart", name);
+    app_handle.request_restart();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn table_exists(pool: &Pool<Sqlite>, name: &str) -> bool {
+        sqlx::query("SELECT 1 FROM sqlite_master WHERE name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn down_migrations_revert_and_reapply() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let migrator = get_migrator();
+        migrator.run(&pool).await.unwrap();
+        assert!(table_exists(&pool, "clear_downloads_pause_reason").await);
+
+        revert_migrations(&pool, 4).await.unwrap();
+        assert!(!table_exists(&pool, "clear_downloads_pause_reason").await);
+        assert_eq!(get_pending_migrations(&pool, &migrator).await, [5]);
+
+        revert_migrations(&pool, 0).await.unwrap();
+        assert!(!table_exists(&pool, "downloads").await);
+        assert_eq!(
+            get_pending_migrations(&pool, &migrator).await,
+            [1, 2, 3, 4, 5]
+        );
+
+        migrator.run(&pool).await.unwrap();
+        assert!(table_exists(&pool, "downloads").await);
+        assert!(get_pending_migrations(&pool, &migrator).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_schema_versions() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        assert!(revert_migrations(&pool, -1).await.is_err());
+        assert!(revert_migrations(&pool, i64::MAX).await.is_err());
+    }
+}
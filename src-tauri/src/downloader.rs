@@ -0,0 +1,667 @@
+use crate::{
+    database::get_pool, extension, models::DownloadStatus, process::is_ytdlp_process,
+    PAUSE_REASON_USER,
+};
+use log::{error, info, warn};
+use neodlp_protocol::DownloadEvent;
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::{
+    collections::HashMap,
+    process::{Command as StdCommand, Stdio},
+    time::{Duration, Instant},
+};
+use tauri::{ipc::Channel, AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::ShellExt;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    sync::Mutex,
+};
+
+// Minimum interval between two progress writes to the database for the same download
+const PROGRESS_WRITE_INTERVAL: Duration = Duration::from_millis(500);
+
+struct ActiveDownload {
+    // Unknown while the slot is reserved and yt-dlp is being spawned
+    pid: Option<u32>,
+    stop_requested: bool,
+}
+
+/// Owns every yt-dlp process spawned by the backend, keyed by `download_id`.
+#[derive(Default)]
+pub struct DownloadManager {
+    downloads: Mutex<HashMap<String, ActiveDownload>>,
+}
+
+impl DownloadManager {
+    /// Claims the slot of a download before its process is spawned, `false` if
+    /// it is already taken so the same download can't be started twice.
+    async fn reserve(&self, download_id: &str) -> bool {
+        let mut downloads = self.downloads.lock().await;
+        if downloads.contains_key(download_id) {
+            return false;
+        }
+        downloads.insert(
+            download_id.to_string(),
+            ActiveDownload {
+                pid: None,
+                stop_requested: false,
+            },
+        );
+        true
+    }
+
+    /// Frees the slot of a download and returns whether a stop was requested.
+    async fn release(&self, download_id: &str) -> bool {
+        self.downloads
+            .lock()
+            .await
+            .remove(download_id)
+            .is_some_and(|download| download.stop_requested)
+    }
+
+    /// Records the PID of a reserved download and returns whether a stop was
+    /// requested while it was being spawned.
+    async fn set_pid(&self, download_id: &str, pid: u32) -> bool {
+        let mut downloads = self.downloads.lock().await;
+        downloads.get_mut(download_id).is_some_and(|download| {
+            download.pid = Some(pid);
+            download.stop_requested
+        })
+    }
+
+    pub async fn get_pid(&self, download_id: &str) -> Option<u32> {
+        self.downloads
+            .lock()
+            .await
+            .get(download_id)
+            .and_then(|download| download.pid)
+    }
+
+    /// Marks a download as intentionally stopped and returns its PID, so the
+    /// supervisor does not report the non-zero exit code as an error.
+    pub async fn request_stop(&self, download_id: &str) -> Option<u32> {
+        let mut downloads = self.downloads.lock().await;
+        downloads.get_mut(download_id).and_then(|download| {
+            download.stop_requested = true;
+            download.pid
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DownloadProgress {
+    pub status: Option<String>,
+    pub progress: Option<f64>,
+    pub speed: Option<f64>,
+    pub downloaded: Option<i64>,
+    pub total: Option<i64>,
+    pub eta: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DownloadProgressEvent {
+    download_id: String,
+    #[serde(flatten)]
+    progress: DownloadProgress,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DownloadFinishedEvent {
+    download_id: String,
+    exit_code: Option<i32>,
+    download_status: DownloadStatus,
+    filepath: Option<String>,
+}
+
+fn convert_to_bytes(value: f64, unit: &str) -> f64 {
+    match unit {
+        "KiB" => value * 1024.0,
+        "MiB" => value * 1024.0 * 1024.0,
+        "GiB" => value * 1024.0 * 1024.0 * 1024.0,
+        _ => value,
+    }
+}
+
+fn split_size_unit(value: &str) -> Option<(f64, &str)> {
+    let unit_start = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(unit_start);
+    number.parse().ok().map(|number| (number, unit))
+}
+
+// Parses yt-dlp `--progress-template` output:
+// status:downloading,progress: 75.1%,speed:1022692.427018,downloaded:30289474,total:40331784,eta:9
+fn parse_template_progress(line: &str) -> DownloadProgress {
+    let mut progress = DownloadProgress {
+        status: Some("downloading".to_string()),
+        ..Default::default()
+    };
+
+    for pair in line.split(',') {
+        let Some((key, value)) = pair.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "status" => progress.status = Some(value.to_string()),
+            "progress" => progress.progress = value.trim_end_matches('%').trim().parse().ok(),
+            "speed" => progress.speed = value.parse().ok(),
+            "downloaded" => progress.downloaded = value.parse().ok(),
+            "total" => progress.total = value.parse().ok(),
+            "eta" => progress.eta = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    progress
+}
+
+// Parses aria2c progress output: [#99f72b 2.5MiB/3.4MiB(75%) CN:1 DL:503KiB ETA:1s]
+fn parse_aria2_progress(line: &str) -> DownloadProgress {
+    let mut progress = DownloadProgress {
+        status: Some("downloading".to_string()),
+        ..Default::default()
+    };
+
+    let body = line.trim_start_matches('[').trim_end_matches(']');
+    for token in body.split_whitespace() {
+        if let Some(value) = token.strip_prefix("DL:") {
+            progress.speed = split_size_unit(value).map(|(n, unit)| convert_to_bytes(n, unit));
+        } else if let Some(value) = token.strip_prefix("ETA:") {
+            progress.eta = value.trim_end_matches('s').parse().ok();
+        } else if let Some((sizes, percent)) = token.split_once('(') {
+            progress.progress = percent
+                .trim_end_matches(")")
+                .trim_end_matches('%')
+                .parse()
+                .ok();
+            if let Some((downloaded, total)) = sizes.split_once('/') {
+                progress.downloaded =
+                    split_size_unit(downloaded).map(|(n, unit)| convert_to_bytes(n, unit) as i64);
+                progress.total =
+                    split_size_unit(total).map(|(n, unit)| convert_to_bytes(n, unit) as i64);
+            }
+        }
+    }
+
+    progress
+}
+
+pub fn parse_progress_line(line: &str) -> Option<DownloadProgress> {
+    // Combined aria2c and yt-dlp format, only the yt-dlp part is relevant
+    if let Some(index) = line.find("]status:") {
+        return Some(parse_template_progress(&line[index + 1..]));
+    }
+    if line.starts_with("[#") && line.contains('%') {
+        return Some(parse_aria2_progress(line));
+    }
+    if line.starts_with("status:") {
+        return Some(parse_template_progress(line));
+    }
+    None
+}
+
+fn parse_playlist_item(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("[download] Downloading item ")?;
+    let (current, total) = rest.trim().split_once(" of ")?;
+    let current: u32 = current.trim().parse().ok()?;
+    let total: u32 = total.trim().parse().ok()?;
+    Some(format!("{}/{}", current, total))
+}
+
+fn build_ytdlp_command(app_handle: &AppHandle, args: &[String]) -> Result<Command, String> {
+    let std_command = if std::env::var("FLATPAK").is_ok() {
+        // On Flatpak yt-dlp is installed and auto-updated inside the app data dir
+        let ytdlp_path = app_handle
+            .path()
+            .data_dir()
+            .map_err(|e| format!("Failed to get data dir: {}", e))?
+            .join("yt-dlp")
+            .join("yt-dlp");
+        let mut command = StdCommand::new(ytdlp_path);
+        command.args(args);
+        command
+    } else {
+        let sidecar = app_handle
+            .shell()
+            .sidecar("yt-dlp")
+            .map_err(|e| format!("Failed to resolve yt-dlp sidecar: {}", e))?;
+        StdCommand::from(sidecar.args(args))
+    };
+
+    let mut command = Command::from(std_command);
+    command
+        .env("PYTHONUNBUFFERED", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(false);
+
+    // Own process group, so yt-dlp and its ffmpeg/aria2c/deno children can be signaled together
+    #[cfg(unix)]
+    command.process_group(0);
+
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    Ok(command)
+}
+
+// yt-dlp keeps printing progress until it exits, only running downloads take it, so a
+// suspended or stopped download is never flipped back to `downloading`
+async fn write_progress(
+    pool: &Pool<Sqlite>,
+    download_id: &str,
+    pid: u32,
+    progress: &DownloadProgress,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE downloads SET
+            download_status = 'downloading',
+            process_id = ?,
+            status = ?,
+            progress = COALESCE(?, progress),
+            speed = ?,
+            downloaded = COALESCE(?, downloaded),
+            total = COALESCE(?, total),
+            eta = ?
+        WHERE download_id = ? AND download_status IN ('starting', 'downloading')",
+    )
+    .bind(pid as i64)
+    .bind(&progress.status)
+    .bind(progress.progress)
+    .bind(progress.speed)
+    .bind(progress.downloaded)
+    .bind(progress.total)
+    .bind(progress.eta)
+    .bind(download_id)
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+/// Spawns yt-dlp with the given arguments for an existing `downloads` row and
+/// supervises it in the background until it exits.
+pub async fn spawn_download(
+    app_handle: &AppHandle,
+    download_id: &str,
+    args: Vec<String>,
+) -> Result<u32, String> {
+    let manager = app_handle.state::<DownloadManager>();
+    if !manager.reserve(download_id).await {
+        return Err(format!("Download {} is already running", download_id));
+    }
+
+    let spawned = get_pool(app_handle).and_then(|pool| {
+        let child = build_ytdlp_command(app_handle, &args)?
+            .spawn()
+            .map_err(|e| format!("Failed to spawn yt-dlp: {}", e))?;
+        Ok((pool, child))
+    });
+    let (pool, mut child) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            manager.release(download_id).await;
+            return Err(e);
+        }
+    };
+    let Some(pid) = child.id() else {
+        manager.release(download_id).await;
+        return Err("yt-dlp exited before its PID could be read".to_string());
+    };
+
+    info!("Started yt-dlp download {} with PID: {}", download_id, pid);
+    if manager.set_pid(download_id, pid).await {
+        info!("Download {} was stopped while starting", download_id);
+        if let Err(e) = crate::kill_all_process(pid as i32).await {
+            warn!("Failed to stop download {}: {}", download_id, e);
+        }
+    }
+
+    // A download stopped while starting was already marked by `stop_download`, and the
+    // supervisor leaves it alone, so it must not be put back to `starting` here
+    let download_args = serde_json::to_string(&args).map_err(|e| e.to_string())?;
+    if let Err(e) = sqlx::query(
+        "UPDATE downloads SET download_status = 'starting', process_id = ?, queue_index = NULL, download_args = ?
+        WHERE download_id = ? AND download_status NOT IN ('paused', 'errored')",
+    )
+    .bind(pid as i64)
+    .bind(download_args)
+    .bind(download_id)
+    .execute(&pool)
+    .await
+    {
+        error!("Failed to record PID for download {}: {}", download_id, e);
+    }
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    if let Some(stderr) = stderr {
+        let download_id = download_id.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if !line.trim().is_empty() {
+                    warn!("YT-DLP Download {}: {}", download_id, line);
+                }
+            }
+        });
+    }
+
+    let app_handle = app_handle.clone();
+    let download_id = download_id.to_string();
+    tokio::spawn(async move {
+        let mut filepath: Option<String> = None;
+
+        if let Some(stdout) = stdout {
+            let mut lines = BufReader::new(stdout).lines();
+            let mut last_write: Option<Instant> = None;
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(progress) = parse_progress_line(&line) {
+                    let finished = progress.status.as_deref() == Some("finished");
+                    let due = last_write.is_none_or(|t| t.elapsed() >= PROGRESS_WRITE_INTERVAL);
+                    if !(due || finished) {
+                        continue;
+                    }
+                    last_write = Some(Instant::now());
+
+                    if let Err(e) = write_progress(&pool, &download_id, pid, &progress).await {
+                        error!(
+                            "Failed to update progress for download {}: {}",
+                            download_id, e
+                        );
+                    }
+                    let event = DownloadEvent::Progress {
+                        download_id: download_id.clone(),
+                        progress: progress.progress,
+                        speed: progress.speed,
+                        eta: progress.eta,
+                    };
+                    extension::publish(&app_handle, event).await;
+                    let _ = app_handle.emit(
+                        "download-progress",
+                        DownloadProgressEvent {
+                            download_id: download_id.clone(),
+                            progress,
+                        },
+                    );
+                } else if let Some(item) = parse_playlist_item(&line) {
+                    if let Err(e) =
+                        sqlx::query("UPDATE downloads SET item = ? WHERE download_id = ?")
+                            .bind(&item)
+                            .bind(&download_id)
+                            .execute(&pool)
+                            .await
+                    {
+                        error!(
+                            "Failed to update playlist item for download {}: {}",
+                            download_id, e
+                        );
+                    }
+                } else if let Some(path) = line.strip_prefix("Finalpath: ") {
+                    filepath = Some(path.trim().trim_matches('"').to_string());
+                } else if !line.trim().is_empty() {
+                    info!("YT-DLP Download {}: {}", download_id, line);
+                }
+            }
+        }
+
+        let exit_status = child.wait().await;
+        let stop_requested = app_handle
+            .state::<DownloadManager>()
+            .release(&download_id)
+            .await;
+
+        let exit_code = exit_status.as_ref().ok().and_then(|status| status.code());
+        let succeeded = exit_status.as_ref().is_ok_and(|status| status.success());
+        match &exit_status {
+            Ok(status) => info!("yt-dlp download {} exited with {}", download_id, status),
+            Err(e) => error!("Failed to wait for yt-dlp download {}: {}", download_id, e),
+        }
+
+        let download_status = if succeeded {
+            DownloadStatus::Completed
+        } else if stop_requested {
+            DownloadStatus::Paused
+        } else {
+            DownloadStatus::Errored
+        };
+
+        // A download that finished anyway is completed, otherwise whoever
+        // requested the stop is responsible for the final status
+        if succeeded || !stop_requested {
+            let ext = filepath
+                .as_deref()
+                .and_then(|path| path.rsplit_once('.'))
+                .map(|(_, ext)| ext.to_string());
+            if let Err(e) = sqlx::query(
+                "UPDATE downloads SET
+                    download_status = ?,
+                    process_id = NULL,
+                    filepath = COALESCE(?, filepath),
+                    ext = COALESCE(?, ext)
+                WHERE download_id = ?",
+            )
+            .bind(download_status.as_str())
+            .bind(&filepath)
+            .bind(&ext)
+            .bind(&download_id)
+            .execute(&pool)
+            .await
+            {
+                error!(
+                    "Failed to update final status for download {}: {}",
+                    download_id, e
+                );
+            }
+        }
+
+        // A slot got freed, let the scheduler start the next queued download
+        crate::queue::notify_scheduler(&app_handle);
+
+        let event = match download_status {
+            DownloadStatus::Completed => Some(DownloadEvent::Finished {
+                download_id: download_id.clone(),
+                filepath: filepath.clone(),
+            }),
+            DownloadStatus::Errored => Some(DownloadEvent::Failed {
+                download_id: download_id.clone(),
+                message: match exit_code {
+                    Some(code) => format!("yt-dlp exited with code {}", code),
+                    None => "yt-dlp was terminated".to_string(),
+                },
+            }),
+            _ => None,
+        };
+        if let Some(event) = event {
+            extension::publish(&app_handle, event).await;
+        }
+
+        let _ = app_handle.emit(
+            "download-finished",
+            DownloadFinishedEvent {
+                download_id,
+                exit_code,
+                download_status,
+                filepath,
+            },
+        );
+    });
+
+    Ok(pid)
+}
+
+// Tolerance between the process start and the last update of its row
+const PROCESS_START_TOLERANCE: i64 = 5;
+
+/// Moves downloads left at `downloading`/`starting`/`suspended` by a crash to
+/// `interrupted` when their stored PID no longer belongs to the yt-dlp process
+/// that was started for them, and forgets the PID of `interrupted` downloads
+/// whose process survived the last shutdown once it is gone. Must run before
+/// anything signals stored PIDs.
+pub async fn reconcile_stale_downloads(pool: &Pool<Sqlite>) -> Result<u64, String> {
+    let rows = sqlx::query(
+        "SELECT id, download_id, process_id, CAST(strftime('%s', updated_at) AS INTEGER) AS updated_at
+        FROM downloads
+        WHERE download_status IN ('downloading', 'starting', 'suspended')
+            OR (download_status = 'interrupted' AND process_id IS NOT NULL)",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut interrupted = 0;
+    for row in rows {
+        let id: i64 = row.get("id");
+        let download_id: String = row.get("download_id");
+        let process_id: Option<i32> = row.get("process_id");
+        let updated_at: Option<i64> = row.get("updated_at");
+
+        let is_alive = process_id.is_some_and(|pid| {
+            is_ytdlp_process(pid, updated_at.map(|t| t + PROCESS_START_TOLERANCE))
+        });
+        if is_alive {
+            info!(
+                "Download {} still has a running yt-dlp process ({:?})",
+                download_id, process_id
+            );
+            continue;
+        }
+
+        warn!(
+            "Download {} has a stale process ({:?}), marking as interrupted",
+            download_id, process_id
+        );
+        interrupted += sqlx::query(
+            "UPDATE downloads SET download_status = 'interrupted', process_id = NULL, speed = NULL, eta = NULL WHERE id = ?",
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    }
+
+    Ok(interrupted)
+}
+
+/// Runs yt-dlp with `--dump-single-json` style arguments and returns the JSON
+/// it printed. The PID is sent through `on_spawn` as soon as the process has
+/// started, so the frontend can cancel the search with `kill_all_process`.
+#[tauri::command]
+pub async fn fetch_video_metadata(
+    app_handle: AppHandle,
+    args: Vec<String>,
+    on_spawn: Channel<u32>,
+) -> Result<serde_json::Value, String> {
+    info!("Fetching metadata with args: {}", args.join(" "));
+    let mut child = build_ytdlp_command(&app_handle, &args)?
+        .spawn()
+        .map_err(|e| format!("Failed to spawn yt-dlp: {}", e))?;
+    if let Some(pid) = child.id() {
+        if let Err(e) = on_spawn.send(pid) {
+            warn!("Failed to report metadata fetch PID {}: {}", pid, e);
+        }
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if !line.trim().is_empty() {
+                    warn!("YT-DLP: {}", line);
+                }
+            }
+        });
+    }
+
+    let mut json = None;
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.starts_with('{') {
+                json = Some(line);
+            } else if !line.trim().is_empty() {
+                info!("YT-DLP: {}", line);
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for yt-dlp: {}", e))?;
+    if !status.success() {
+        warn!("yt-dlp exited with {} while fetching metadata", status);
+    }
+    let json = json.ok_or_else(|| format!("yt-dlp printed no metadata ({})", status))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse metadata JSON: {}", e))
+}
+
+#[tauri::command]
+pub async fn start_download(
+    app_handle: AppHandle,
+    download_id: String,
+    args: Vec<String>,
+) -> Result<u32, String> {
+    info!(
+        "Starting yt-dlp download {} with args: {}",
+        download_id,
+        args.join(" ")
+    );
+    spawn_download(&app_handle, &download_id, args).await
+}
+
+/// Stops a download started by the backend and records it as paused by the
+/// user, or as errored if its process could not be signaled. A download that
+/// isn't running (e.g. still queued) is only marked as paused.
+#[tauri::command]
+pub async fn stop_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<Vec<i32>, String> {
+    let pool = get_pool(&app_handle)?;
+    let result = match manager.request_stop(&download_id).await {
+        Some(pid) => crate::kill_all_process(pid as i32).await,
+        None => Ok(Vec::new()),
+    };
+    let download_status = match &result {
+        Ok(_) => DownloadStatus::Paused,
+        Err(e) => {
+            error!("Failed to stop download {}: {}", download_id, e);
+            DownloadStatus::Errored
+        }
+    };
+
+    // Never overwrite a download that completed before the signal arrived
+    sqlx::query(
+        "UPDATE downloads SET download_status = ?, pause_reason = ?, process_id = NULL, speed = NULL, eta = NULL
+        WHERE download_id = ? AND download_status != 'completed'",
+    )
+    .bind(download_status.as_str())
+    .bind(PAUSE_REASON_USER)
+    .bind(&download_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    result
+}
+
+#[tauri::command]
+pub async fn list_active_downloads(
+    manager: State<'_, DownloadManager>,
+) -> Result<HashMap<String, u32>, String> {
+    let downloads = manager.downloads.lock().await;
+    Ok(downloads
+        .iter()
+        .filter_map(|(download_id, download)| download.pid.map(|pid| (download_id.clone(), pid)))
+        .collect())
+}
@@ -0,0 +1,247 @@
+use crate::{
+    database::get_pool,
+    models::{DownloadDetails, DownloadFilter, DownloadStatus},
+    repository, WebSocketState, WEBSOCKET_LOG_TARGET,
+};
+use futures_util::SinkExt;
+use log::warn;
+use neodlp_protocol::{
+    is_compatible, DownloadEvent, DownloadSummary, ErrorCode, Request, Response, ResponseFrame,
+    PROTOCOL_VERSION,
+};
+use std::{collections::HashSet, sync::Arc};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+
+// Downloads reported by `status` when the extension doesn't ask for a specific one
+const UNFINISHED_STATUSES: [DownloadStatus; 6] = [
+    DownloadStatus::Queued,
+    DownloadStatus::Starting,
+    DownloadStatus::Downloading,
+    DownloadStatus::Paused,
+    DownloadStatus::Suspended,
+    DownloadStatus::Interrupted,
+];
+
+/// Downloads a client receives events about, see `Request::Subscribe`.
+#[derive(Debug, Default)]
+pub enum Subscription {
+    #[default]
+    None,
+    All,
+    Downloads(HashSet<String>),
+}
+
+impl Subscription {
+    fn add(&mut self, download_id: Option<String>) {
+        match (download_id, &mut *self) {
+            (None, _) => *self = Subscription::All,
+            (Some(_), Subscription::All) => {}
+            (Some(download_id), Subscription::Downloads(download_ids)) => {
+                download_ids.insert(download_id);
+            }
+            (Some(download_id), Subscription::None) => {
+                *self = Subscription::Downloads(HashSet::from([download_id]))
+            }
+        }
+    }
+
+    fn includes(&self, download_id: &str) -> bool {
+        match self {
+            Subscription::None => false,
+            Subscription::All => true,
+            Subscription::Downloads(download_ids) => download_ids.contains(download_id),
+        }
+    }
+}
+
+// Schemes of the origins browsers send for extension pages, any other origin is a web page
+const EXTENSION_ORIGIN_SCHEMES: [&str; 3] = [
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-web-extension://",
+];
+
+/// Web pages can open WebSockets to localhost too, and browsers always send their
+/// `Origin`. The native messaging host sends none.
+pub fn is_allowed_origin(origin: Option<&str>) -> bool {
+    match origin {
+        None => true,
+        Some(origin) => EXTENSION_ORIGIN_SCHEMES
+            .iter()
+            .any(|scheme| origin.starts_with(scheme)),
+    }
+}
+
+/// Compares the token from a `hello` with the app's, in constant time.
+pub fn is_authorized(token: Option<&str>, expected: &str) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    if expected.is_empty() || token.len() != expected.len() {
+        return false;
+    }
+    token
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+pub fn hello(app_handle: &AppHandle, version: u32) -> Response {
+    if !is_compatible(version) {
+        return Response::error(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "Protocol version {} is not supported, the app speaks version {}",
+                version, PROTOCOL_VERSION
+            ),
+        );
+    }
+    Response::Hello {
+        version: PROTOCOL_VERSION,
+        app_version: app_handle.package_info().version.to_string(),
+    }
+}
+
+fn to_summary(download: DownloadDetails) -> DownloadSummary {
+    DownloadSummary {
+        download_id: download.download.download_id,
+        status: download.download.download_status.to_string(),
+        progress: download.download.progress,
+        title: Some(download.title),
+        url: Some(download.url),
+    }
+}
+
+async fn status(app_handle: &AppHandle, download_id: Option<&str>) -> Response {
+    let pool = match get_pool(app_handle) {
+        Ok(pool) => pool,
+        Err(e) => return Response::error(ErrorCode::Internal, e),
+    };
+
+    let downloads = match download_id {
+        Some(download_id) => match repository::get_download(&pool, download_id).await {
+            Ok(Some(download)) => vec![download],
+            Ok(None) => {
+                return Response::error(
+                    ErrorCode::NotFound,
+                    format!("Download {} not found", download_id),
+                )
+            }
+            Err(e) => return Response::error(ErrorCode::Internal, e.to_string()),
+        },
+        None => {
+            let filter = DownloadFilter {
+                statuses: UNFINISHED_STATUSES.to_vec(),
+                per_page: Some(u32::MAX),
+                ..Default::default()
+            };
+            match repository::list_downloads(&pool, &filter).await {
+                Ok(page) => page.items,
+                Err(e) => return Response::error(ErrorCode::Internal, e.to_string()),
+            }
+        }
+    };
+
+    Response::Status {
+        downloads: downloads.into_iter().map(to_summary).collect(),
+    }
+}
+
+// Applies a `subscribe` or `unsubscribe` to the client that sent it
+async fn update_subscription(
+    app_handle: &AppHandle,
+    client_id: u64,
+    update: impl FnOnce(&mut Subscription),
+    response: Response,
+) -> Response {
+    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+    let mut state = state.lock().await;
+    match state.clients.get_mut(&client_id) {
+        Some(client) => {
+            update(&mut client.subscription);
+            response
+        }
+        None => Response::error(ErrorCode::Internal, "Client is no longer connected"),
+    }
+}
+
+/// Answers the requests the backend handles on its own. `None` means the request
+/// needs the frontend (anything touching the UI or the frontend's download queue).
+pub async fn handle_request(
+    app_handle: &AppHandle,
+    client_id: u64,
+    request: &Request,
+) -> Option<Response> {
+    match request {
+        Request::Hello { version, .. } => Some(hello(app_handle, *version)),
+        Request::Ping => Some(Response::Pong),
+        Request::Status { download_id } => Some(status(app_handle, download_id.as_deref()).await),
+        Request::Subscribe { download_id } => {
+            let download_id = download_id.clone();
+            let update = |subscription: &mut Subscription| subscription.add(download_id);
+            Some(update_subscription(app_handle, client_id, update, Response::Subscribed).await)
+        }
+        Request::Unsubscribe => {
+            let update = |subscription: &mut Subscription| *subscription = Subscription::None;
+            Some(update_subscription(app_handle, client_id, update, Response::Unsubscribed).await)
+        }
+        Request::Download { .. }
+        | Request::Enqueue { .. }
+        | Request::FetchInfo { .. }
+        | Request::Cancel { .. } => None,
+    }
+}
+
+/// Sends a download event to every client subscribed to that download.
+pub async fn publish(app_handle: &AppHandle, event: DownloadEvent) {
+    let Some(state) = app_handle.try_state::<Arc<Mutex<WebSocketState>>>() else {
+        return;
+    };
+    let download_id = event.download_id().to_string();
+    let message = ResponseFrame::new(None, Response::Event { event }).to_json();
+
+    // Sent after the state lock is released, a slow client must not block everything else
+    let senders: Vec<_> = state
+        .lock()
+        .await
+        .clients
+        .iter()
+        .filter(|(_, client)| client.authenticated && client.subscription.includes(&download_id))
+        .map(|(client_id, client)| (*client_id, client.sender.clone()))
+        .collect();
+
+    for (client_id, sender) in senders {
+        if let Err(e) = sender
+            .lock()
+            .await
+            .send(Message::Text(message.clone().into()))
+            .await
+        {
+            warn!(
+                target: WEBSOCKET_LOG_TARGET,
+                "Failed to send event to client {}: {}", client_id, e
+            );
+        }
+    }
+}
+
+/// Publishes the `queued` event, which carries the whole download summary.
+pub async fn publish_queued(app_handle: &AppHandle, download_id: &str) {
+    let Ok(pool) = get_pool(app_handle) else {
+        return;
+    };
+    match repository::get_download(&pool, download_id).await {
+        Ok(Some(download)) => {
+            let download = to_summary(download);
+            publish(app_handle, DownloadEvent::Queued { download }).await;
+        }
+        Ok(None) => {}
+        Err(e) => warn!(
+            target: WEBSOCKET_LOG_TARGET,
+            "Failed to load download {} for its queued event: {}", download_id, e
+        ),
+    }
+}
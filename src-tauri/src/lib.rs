@@ -1,20 +1,36 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod config;
+mod config_watcher;
+mod database;
+mod downloader;
+mod extension;
 mod migrations;
+mod models;
+mod native_host;
+mod process;
+mod queue;
+mod repository;
+mod settings;
+mod shutdown;
 use base64::{engine::general_purpose::STANDARD, Engine};
-use config::{get_config_path, load_config, save_config, Config};
+use config::{
+    apply_patch, get_config_path, get_port_override, load_config_checked, load_or_create_token,
+    needs_settings_import, save_active_port, save_config, save_launch_command, setting_patch,
+    Config, ConfigInfo, PORT_ENV,
+};
+use database::Database;
+use downloader::DownloadManager;
 use futures_util::{SinkExt, StreamExt};
+use neodlp_protocol::{ErrorCode, Request, RequestFrame, Response, ResponseFrame};
+use models::DownloadStatus;
+use queue::QueueScheduler;
+use shutdown::ShutdownCoordinator;
 use reqwest;
-use serde_json::Value;
-use sqlx::{
-    sqlite::{SqliteConnectOptions, SqlitePool},
-    Pool, Row, Sqlite,
-};
+use sqlx::{Pool, Row, Sqlite};
 use std::{
     collections::{hash_map::DefaultHasher, HashMap},
     env, fs,
     hash::{Hash, Hasher},
-    process::Command as StdCommand,
     sync::{Arc, Mutex as StdMutex},
     time::Duration,
 };
@@ -28,36 +44,100 @@ use tauri_plugin_opener::OpenerExt;
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::{oneshot, Mutex},
-    time::sleep,
+    time::{sleep, timeout, timeout_at},
 };
-use tokio_tungstenite::accept_async;
-use log::{info, error};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{
+            ErrorResponse, Request as HandshakeRequest, Response as HandshakeResponse,
+        },
+        http::StatusCode,
+        Error as WebSocketError,
+    },
+};
+use log::{error, info, warn};
+#[cfg(windows)]
+use std::process::Command as StdCommand;
 
 struct ImageCache(StdMutex<HashMap<String, String>>);
 
-struct ResponseChannel {
-    sender: Option<oneshot::Sender<String>>,
+// Time the frontend gets to answer a message from the extension
+const EXTENSION_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// Time a new connection gets to authenticate with its `hello`
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+// Log target of the extension WebSocket server, keeps its entries easy to filter
+const WEBSOCKET_LOG_TARGET: &str = "websocket";
+
+type WebSocketSender = futures_util::stream::SplitSink<
+    tokio_tungstenite::WebSocketStream<TcpStream>,
+    tokio_tungstenite::tungstenite::Message,
+>;
+
+// One connected extension (through its native messaging host). The sender has its own
+// lock, clone it out of the state so a slow client never holds up the whole state
+struct WebSocketClient {
+    sender: Arc<Mutex<WebSocketSender>>,
+    subscription: extension::Subscription,
+    // Set by a valid `hello`, nothing is sent to the client on its own before that
+    authenticated: bool,
+}
+
+// Payload of the `websocket-message` event, a request the frontend has to answer
+#[derive(Clone, serde::Serialize)]
+struct ExtensionRequestEvent {
+    client_id: u64,
+    request_id: u64,
+    #[serde(flatten)]
+    request: Request,
+}
+
+// A message forwarded to the frontend that is still waiting for its response
+struct PendingRequest {
+    client_id: u64,
+    sender: oneshot::Sender<String>,
+}
+
+// Failure counters since launch, shown on the extension settings page
+#[derive(Debug, Default, Clone, serde::Serialize)]
+struct WebSocketStats {
+    connections: u64,
+    handshake_failures: u64,
+    rejected_origins: u64,
+    unauthorized: u64,
+    invalid_messages: u64,
+    emit_failures: u64,
+    timeouts: u64,
 }
 
 struct WebSocketState {
-    sender: Option<
-        futures_util::stream::SplitSink<
-            tokio_tungstenite::WebSocketStream<TcpStream>,
-            tokio_tungstenite::tungstenite::Message,
-        >,
-    >,
-    response_channel: ResponseChannel,
+    clients: HashMap<u64, WebSocketClient>,
+    next_client_id: u64,
+    pending_requests: HashMap<u64, PendingRequest>,
+    next_request_id: u64,
     server_abort: Option<tokio::sync::oneshot::Sender<()>>,
+    // Port the server fell back to because the configured one was taken, only kept for this run
+    fallback_port: Option<u16>,
+    // Expected in the `hello` of every connection, empty if it could not be created
+    auth_token: String,
+    stats: WebSocketStats,
     config: Config,
+    // Why the config file was not used at startup, cleared once a valid config is saved
+    config_load_error: Option<String>,
 }
 
+// Stored in `downloads.pause_reason` by every pause, the v5 trigger clears it once the
+// download leaves `paused`. Rows paused before v5 have no reason and count as user pauses
+const PAUSE_REASON_QUIT: &str = "quit";
+const PAUSE_REASON_USER: &str = "user";
+
 #[derive(Debug, serde::Serialize)]
 struct DownloadState {
     id: i32,
     download_id: String,
-    download_status: String,
+    download_status: DownloadStatus,
     process_id: Option<i32>,
-    status: String,
+    status: Option<String>,
 }
 
 async fn is_port_available(port: u16) -> bool {
@@ -82,17 +162,46 @@ async fn wait_for_port_availability(port: u16, max_attempts: u32) -> Result<(),
     ))
 }
 
-async fn start_websocket_server(app_handle: tauri::AppHandle, port: u16) -> Result<(), String> {
+#[derive(Debug, Clone, serde::Serialize)]
+struct WebSocketPortFallback {
+    requested_port: u16,
+    port: u16,
+}
+
+// Binds the first free port of the config's fallback range, `None` if the fallback is off
+async fn bind_fallback_port(
+    app_handle: &tauri::AppHandle,
+    busy_port: u16,
+) -> Option<(TcpListener, u16)> {
+    let ports = {
+        let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+        let state = state.lock().await;
+        state.config.websocket_ports()
+    };
+    for port in ports.into_iter().filter(|port| *port != busy_port) {
+        if let Ok(listener) = TcpListener::bind(format!("127.0.0.1:{}", port)).await {
+            return Some((listener, port));
+        }
+    }
+    None
+}
+
+async fn start_websocket_server(app_handle: tauri::AppHandle, mut port: u16) -> Result<(), String> {
     let addr = format!("127.0.0.1:{}", port);
+    let requested_port = port;
 
     // First ensure any existing server is stopped
     {
         let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
         let mut state = state.lock().await;
+        state.fallback_port = None;
         if let Some(old_abort) = state.server_abort.take() {
             let _ = old_abort.send(());
-            // Wait for the port to become available
-            wait_for_port_availability(port, 6).await?; // Try for 3 seconds (6 attempts * 500ms)
+            // Wait for the port to become available (6 attempts * 500ms), binding below
+            // reports it if it doesn't
+            if let Err(e) = wait_for_port_availability(port, 6).await {
+                warn!(target: WEBSOCKET_LOG_TARGET, "{}", e);
+            }
         }
     }
 
@@ -102,9 +211,32 @@ async fn start_websocket_server(app_handle: tauri::AppHandle, port: u16) -> Resu
         Err(_e) => {
             // One final attempt to wait and retry
             sleep(Duration::from_secs(1)).await;
-            TcpListener::bind(&addr)
-                .await
-                .map_err(|e| format!("Failed to bind to port {}: {}", port, e))?
+            match TcpListener::bind(&addr).await {
+                Ok(l) => l,
+                Err(e) => {
+                    let error = format!("Failed to bind to port {}: {}", port, e);
+                    let Some((listener, fallback_port)) =
+                        bind_fallback_port(&app_handle, port).await
+                    else {
+                        return Err(error);
+                    };
+                    warn!(
+                        target: WEBSOCKET_LOG_TARGET,
+                        "{}, falling back to port {}", error, fallback_port
+                    );
+                    // The config keeps the requested port, `neodlp-msghost` finds this one in
+                    // the port file or by walking the same range
+                    let _ = app_handle.emit(
+                        "websocket-port-fallback",
+                        WebSocketPortFallback {
+                            requested_port,
+                            port: fallback_port,
+                        },
+                    );
+                    port = fallback_port;
+                    listener
+                }
+            }
         }
     };
 
@@ -115,11 +247,16 @@ async fn start_websocket_server(app_handle: tauri::AppHandle, port: u16) -> Resu
         let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
         let mut state = state.lock().await;
         state.server_abort = Some(abort_sender);
+        state.fallback_port = (port != requested_port).then_some(port);
+    }
+
+    if let Err(e) = save_active_port(port) {
+        warn!(target: WEBSOCKET_LOG_TARGET, "Failed to record the active port: {}", e);
     }
 
     // Spawn the server task
     tokio::spawn(async move {
-        println!("Starting WebSocket server on port {}", port);
+        info!(target: WEBSOCKET_LOG_TARGET, "Starting WebSocket server on port {}", port);
         loop {
             tokio::select! {
                 accept_result = listener.accept() => {
@@ -129,13 +266,19 @@ async fn start_websocket_server(app_handle: tauri::AppHandle, port: u16) -> Resu
                             tokio::spawn(handle_connection(stream, app_handle));
                         }
                         Err(e) => {
-                            println!("Error accepting connection: {}", e);
+                            error!(
+                                target: WEBSOCKET_LOG_TARGET,
+                                "Error accepting connection: {}", e
+                            );
                             break;
                         }
                     }
                 }
                 _ = &mut abort_receiver => {
-                    println!("WebSocket server shutting down on port {}...", port);
+                    info!(
+                        target: WEBSOCKET_LOG_TARGET,
+                        "WebSocket server shutting down on port {}...", port
+                    );
                     break;
                 }
             }
@@ -154,18 +297,20 @@ async fn restart_websocket_server(
 ) -> Result<(), String> {
     let port = {
         let state = state.lock().await;
-        state.config.port
+        state.config.effective_port()
     };
 
-    println!("Restarting WebSocket server on port {}", port);
+    info!(target: WEBSOCKET_LOG_TARGET, "Restarting WebSocket server on port {}", port);
     // Start the server (this will also handle stopping the old one)
     start_websocket_server(app_handle, port).await
 }
 
 #[tauri::command]
-async fn get_config(state: tauri::State<'_, Arc<Mutex<WebSocketState>>>) -> Result<Config, String> {
+async fn get_config(
+    state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
+) -> Result<ConfigInfo, String> {
     let state = state.lock().await;
-    Ok(state.config.clone())
+    Ok(ConfigInfo::new(state.config.clone(), state.fallback_port))
 }
 
 #[tauri::command]
@@ -196,25 +341,64 @@ fn get_appimage_path() -> Option<String> {
     std::env::var("APPDIR").ok()
 }
 
+// Holds the state lock from read to write, so concurrent patches can't drop each other's changes
+async fn apply_config_patch(
+    app_handle: &tauri::AppHandle,
+    patch: &serde_json::Value,
+) -> Result<Config, String> {
+    let (config, port_changed) = {
+        let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+        let mut state = state.lock().await;
+        let config = apply_patch(&state.config, patch)?;
+        if config == state.config {
+            return Ok(config);
+        }
+        save_config(&config)?;
+        let port_changed = config.effective_port() != state.config.effective_port();
+        state.config = config.clone();
+        state.config_load_error = None;
+        (config, port_changed)
+    };
+
+    if port_changed {
+        start_websocket_server(app_handle.clone(), config.effective_port()).await?;
+    }
+    Ok(config)
+}
+
 #[tauri::command]
-async fn update_config(
-    new_config: Config,
-    state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
+async fn patch_config(
+    patch: serde_json::Value,
     app_handle: tauri::AppHandle,
 ) -> Result<Config, String> {
-    // Save the new config first
-    save_config(&new_config)?;
+    apply_config_patch(&app_handle, &patch).await
+}
 
-    // Update the state with new config
-    {
-        let mut state = state.lock().await;
-        state.config = new_config.clone();
+// Called for every setting the frontend saves, the ones kept in the config are mirrored there
+#[tauri::command]
+async fn sync_config_setting(
+    key: String,
+    value: serde_json::Value,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    if let Some(patch) = setting_patch(&key, &value) {
+        apply_config_patch(&app_handle, &patch).await?;
     }
+    Ok(())
+}
 
-    // Start the new server (this will also handle stopping the old one)
-    start_websocket_server(app_handle, new_config.port).await?;
-
-    Ok(new_config)
+// Copies the settings saved before they were kept in the config
+async fn import_settings_into_config(
+    app_handle: &tauri::AppHandle,
+    pool: &Pool<Sqlite>,
+) -> Result<(), String> {
+    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+    let mut state = state.lock().await;
+    let config = settings::import_config_settings(pool, &state.config).await?;
+    save_config(&config)?;
+    state.config = config;
+    state.config_load_error = None;
+    Ok(())
 }
 
 #[tauri::command]
@@ -228,56 +412,128 @@ async fn reset_config(
     {
         let mut state = state.lock().await;
         state.config = config.clone();
+        state.config_load_error = None;
     }
 
-    start_websocket_server(app_handle, config.port).await?;
+    start_websocket_server(app_handle, config.effective_port()).await?;
 
     Ok(config)
 }
 
+#[tauri::command]
+async fn get_config_load_error(
+    state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
+) -> Result<Option<String>, String> {
+    Ok(state.lock().await.config_load_error.clone())
+}
+
+#[tauri::command]
+async fn get_websocket_stats(
+    state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
+) -> Result<WebSocketStats, String> {
+    Ok(state.lock().await.stats.clone())
+}
+
 #[tauri::command]
 async fn send_to_extension(
     message: String,
+    client_id: Option<u64>,
     state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
 ) -> Result<(), String> {
-    let mut state = state.lock().await;
-    if let Some(sender) = &mut state.sender {
-        sender
+    // Send to one client, or broadcast to all of them when no client is given
+    let senders: Vec<_> = {
+        let state = state.lock().await;
+        let mut clients = state
+            .clients
+            .iter()
+            .filter(|(_, client)| client.authenticated)
+            .peekable();
+        if clients.peek().is_none() {
+            return Err("No active WebSocket connection".to_string());
+        }
+        let senders: Vec<_> = clients
+            .filter(|(id, _)| client_id.is_none_or(|client_id| client_id == **id))
+            .map(|(id, client)| (*id, client.sender.clone()))
+            .collect();
+        if let Some(client_id) = client_id.filter(|_| senders.is_empty()) {
+            return Err(format!("No WebSocket connection with ID {}", client_id));
+        }
+        senders
+    };
+
+    let mut sent = 0;
+    for (id, sender) in senders {
+        match sender
+            .lock()
+            .await
             .send(tokio_tungstenite::tungstenite::Message::Text(
-                message.into(),
+                message.clone().into(),
             ))
             .await
-            .map_err(|e| format!("Failed to send message: {}", e))?;
-        Ok(())
-    } else {
-        Err("No active WebSocket connection".to_string())
+        {
+            Ok(()) => sent += 1,
+            Err(e) => warn!(
+                target: WEBSOCKET_LOG_TARGET,
+                "Failed to send message to client {}: {}",
+                id,
+                e
+            ),
+        }
     }
+
+    if sent == 0 {
+        return Err("Failed to send message".to_string());
+    }
+    Ok(())
 }
 
 #[tauri::command]
 async fn receive_frontend_response(
     response: String,
+    request_id: Option<u64>,
     state: tauri::State<'_, Arc<Mutex<WebSocketState>>>,
 ) -> Result<(), String> {
     let mut state = state.lock().await;
-    if let Some(sender) = state.response_channel.sender.take() {
-        sender
-            .send(response)
-            .map_err(|e| format!("Failed to send response: {:?}", e))?;
-    }
-    Ok(())
+    // Without a request ID, answer the only pending request (if any)
+    let request_id = match request_id {
+        Some(request_id) => request_id,
+        None => {
+            let mut pending = state.pending_requests.keys().copied();
+            match (pending.next(), pending.next()) {
+                (Some(id), None) => id,
+                (None, _) => return Ok(()),
+                (Some(_), Some(_)) => {
+                    return Err(
+                        "Multiple extension requests are pending, a request ID is required"
+                            .to_string(),
+                    )
+                }
+            }
+        }
+    };
+
+    let request = state
+        .pending_requests
+        .remove(&request_id)
+        .ok_or_else(|| format!("No pending extension request with ID {}", request_id))?;
+    request
+        .sender
+        .send(response)
+        .map_err(|e| format!("Failed to send response: {:?}", e))
 }
 
 #[tauri::command]
-async fn kill_all_process(pid: i32) -> Result<(), String> {
+async fn kill_all_process(pid: i32) -> Result<Vec<i32>, String> {
     #[cfg(unix)]
     {
-        println!("Sending INT signal to process with PID: {}", pid);
-        let mut kill = StdCommand::new("kill")
-            .args(["-s", "SIGINT", &pid.to_string()])
-            .spawn()
-            .map_err(|e| e.to_string())?;
-        kill.wait().map_err(|e| e.to_string())?;
+        info!("Sending INT signal to process tree of PID: {}", pid);
+        let terminated = process::signal_process_tree(pid, libc::SIGINT)?;
+        // Suspended processes only act on the pending SIGINT once they are continued
+        if let Err(e) = process::signal_process_tree(pid, libc::SIGCONT) {
+            warn!("Failed to continue process tree of PID {}: {}", pid, e);
+        }
+        info!("Sent INT signal to PIDs: {:?}", terminated);
+        Ok(terminated)
     }
 
     #[cfg(windows)]
@@ -285,16 +541,15 @@ async fn kill_all_process(pid: i32) -> Result<(), String> {
         use std::os::windows::process::CommandExt;
         const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-        println!("Sending taskkill to process with PID: {}", pid);
+        info!("Sending taskkill to process with PID: {}", pid);
         let mut kill = StdCommand::new("taskkill")
             .args(["/PID", &pid.to_string(), "/F", "/T"]) // /T flag kills the process tree
             .creation_flags(CREATE_NO_WINDOW)
             .spawn()
             .map_err(|e| e.to_string())?;
         kill.wait().map_err(|e| e.to_string())?;
+        Ok(vec![pid])
     }
-
-    Ok(())
 }
 
 #[tauri::command]
@@ -402,25 +657,19 @@ async fn open_link_with_app(
         })
 }
 
-#[tauri::command]
-async fn list_ongoing_downloads(
-    state_mutex: State<'_, StdMutex<Pool<Sqlite>>>,
-) -> Result<Vec<DownloadState>, String> {
-    let pool_clone = {
-        let pool = state_mutex.lock().map_err(|e| e.to_string())?;
-        pool.clone()
-    };
-
-    let qry = "SELECT * FROM downloads WHERE download_status = 'downloading' OR download_status = 'starting' OR download_status = 'queued'";
+async fn fetch_ongoing_downloads(pool: &Pool<Sqlite>) -> Result<Vec<DownloadState>, String> {
+    let qry = "SELECT * FROM downloads WHERE download_status = 'downloading' OR download_status = 'starting' OR download_status = 'queued' OR download_status = 'suspended'";
 
-    match sqlx::query(qry).fetch_all(&pool_clone).await {
+    match sqlx::query(qry).fetch_all(pool).await {
         Ok(rows) => {
             let mut downloads = Vec::new();
             for row in rows {
                 downloads.push(DownloadState {
                     id: row.get("id"),
                     download_id: row.get("download_id"),
-                    download_status: row.get("download_status"),
+                    download_status: DownloadStatus::try_from(
+                        row.get::<String, _>("download_status"),
+                    )?,
                     process_id: row.get("process_id"),
                     status: row.get("status"),
                 });
@@ -431,89 +680,178 @@ async fn list_ongoing_downloads(
     }
 }
 
+#[tauri::command]
+async fn list_ongoing_downloads(
+    database: State<'_, Database>,
+) -> Result<Vec<DownloadState>, String> {
+    let pool_clone = database.pool()?;
+
+    fetch_ongoing_downloads(&pool_clone).await
+}
+
 #[tauri::command]
 async fn pause_ongoing_downloads(
-    state_mutex: State<'_, StdMutex<Pool<Sqlite>>>,
+    database: State<'_, Database>,
+    manager: State<'_, DownloadManager>,
+    reason: Option<String>,
 ) -> Result<(), String> {
+    let reason = reason.unwrap_or_else(|| PAUSE_REASON_USER.to_string());
+
     // Get database connection
-    let pool_clone = {
-        let pool = state_mutex.lock().map_err(|e| e.to_string())?;
-        pool.clone()
-    };
+    let pool_clone = database.pool()?;
 
     // Fetch all ongoing downloads
-    let qry = "SELECT * FROM downloads WHERE download_status = 'downloading' OR download_status = 'starting' OR download_status = 'queued'";
-
-    let downloads = match sqlx::query(qry).fetch_all(&pool_clone).await {
-        Ok(rows) => {
-            let mut downloads = Vec::new();
-            for row in rows {
-                downloads.push(DownloadState {
-                    id: row.get("id"),
-                    download_id: row.get("download_id"),
-                    download_status: row.get("download_status"),
-                    process_id: row.get("process_id"),
-                    status: row.get("status"),
-                });
-            }
-            downloads
-        }
-        Err(e) => return Err(e.to_string()),
-    };
+    let downloads = fetch_ongoing_downloads(&pool_clone).await?;
 
-    println!("Found {} ongoing downloads to pause", downloads.len());
+    info!("Found {} ongoing downloads to pause", downloads.len());
 
     // Process each download
     for download in downloads {
-        println!(
+        info!(
             "Pausing download: {} ({}), Status: {}",
             download.download_id, download.id, download.download_status
         );
 
-        // Kill the process if it exists
-        if let Some(pid) = download.process_id {
-            println!("Terminating process with PID: {}", pid);
+        // Kill the process if it exists, preferring the PID of the backend-owned process
+        let managed_pid = manager.request_stop(&download.download_id).await;
+        if let Some(pid) = managed_pid.map(|pid| pid as i32).or(download.process_id) {
+            info!("Terminating process with PID: {}", pid);
             if let Err(e) = kill_all_process(pid).await {
-                println!("Failed to kill process {}: {}", pid, e);
+                warn!("Failed to kill process {}: {}", pid, e);
             } else {
-                println!("Successfully terminated process {}", pid);
+                info!("Successfully terminated process {}", pid);
             }
         }
 
         // Update the download status in the database
-        let update_qry = "UPDATE downloads SET download_status = 'paused' WHERE id = ?";
+        let update_qry = "UPDATE downloads SET download_status = 'paused', pause_reason = ? WHERE id = ?";
         if let Err(e) = sqlx::query(update_qry)
+            .bind(&reason)
             .bind(download.id)
             .execute(&pool_clone)
             .await
         {
-            println!(
+            error!(
                 "Failed to update download status for ID {}: {}",
                 download.id, e
             );
         } else {
-            println!("Updated download status to 'paused' for ID {}", download.id);
+            info!("Updated download status to 'paused' for ID {}", download.id);
         }
     }
 
     Ok(())
 }
 
+#[cfg(unix)]
+async fn get_download_pid(
+    pool: &Pool<Sqlite>,
+    manager: &DownloadManager,
+    download_id: &str,
+) -> Result<i32, String> {
+    if let Some(pid) = manager.get_pid(download_id).await {
+        return Ok(pid as i32);
+    }
+    sqlx::query("SELECT process_id FROM downloads WHERE download_id = ?")
+        .bind(download_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .and_then(|row| row.get::<Option<i32>, _>("process_id"))
+        .ok_or_else(|| format!("Download {} has no running process", download_id))
+}
+
+#[tauri::command]
+async fn suspend_download(
+    database: State<'_, Database>,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<Vec<i32>, String> {
+    let pool_clone = database.pool()?;
+
+    #[cfg(unix)]
+    {
+        let pid = get_download_pid(&pool_clone, &manager, &download_id).await?;
+        info!("Suspending download: {} (PID: {})", download_id, pid);
+        let suspended = process::signal_process_tree(pid, libc::SIGSTOP)?;
+
+        sqlx::query("UPDATE downloads SET download_status = 'suspended', speed = NULL, eta = NULL WHERE download_id = ?")
+            .bind(&download_id)
+            .execute(&pool_clone)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(suspended)
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (pool_clone, manager, download_id);
+        Err("Suspending downloads is not supported on Windows".to_string())
+    }
+}
+
+#[tauri::command]
+async fn resume_suspended_download(
+    database: State<'_, Database>,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<Vec<i32>, String> {
+    let pool_clone = database.pool()?;
+
+    #[cfg(unix)]
+    {
+        let pid = get_download_pid(&pool_clone, &manager, &download_id).await?;
+        info!("Resuming suspended download: {} (PID: {})", download_id, pid);
+        let resumed = process::signal_process_tree(pid, libc::SIGCONT)?;
+
+        sqlx::query("UPDATE downloads SET download_status = 'downloading' WHERE download_id = ? AND download_status = 'suspended'")
+            .bind(&download_id)
+            .execute(&pool_clone)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(resumed)
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (pool_clone, manager, download_id);
+        Err("Suspending downloads is not supported on Windows".to_string())
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub async fn run() {
     let _ = fix_path_env::fix();
-    let migrations = migrations::get_migrations();
-    let config = load_config();
-    let port = config.port;
+    let import_settings = needs_settings_import();
+    let (config, config_load_error) = load_config_checked();
+    let startup_config_error = config_load_error.clone();
+    let port = config.effective_port();
+    let auth_token = load_or_create_token().unwrap_or_else(|e| {
+        error!("Failed to load the extension authentication token: {}", e);
+        String::new()
+    });
     let websocket_state = Arc::new(Mutex::new(WebSocketState {
-        sender: None,
-        response_channel: ResponseChannel { sender: None },
+        clients: HashMap::new(),
+        next_client_id: 1,
+        pending_requests: HashMap::new(),
+        next_request_id: 1,
         server_abort: None,
+        fallback_port: None,
+        auth_token,
+        stats: WebSocketStats::default(),
         config,
+        config_load_error,
     }));
 
     let args: Vec<String> = env::args().collect();
     let start_hidden = args.contains(&"--hidden".to_string());
+    let rollback_version = args
+        .iter()
+        .position(|arg| arg == "--rollback-database")
+        .map(|i| {
+            args.get(i + 1)
+                .and_then(|version| version.parse::<i64>().ok())
+        });
 
     tauri::Builder::default()
         .plugin(tauri_plugin_log::Builder::new()
@@ -529,10 +867,8 @@ pub async fn run() {
                 let _ = window.set_focus();
             }
         }))
-        .plugin(tauri_plugin_sql::Builder::default()
-            .add_migrations("sqlite:database.db", migrations)
-            .build(),
-        )
+        // Migrations are applied by the backend (see database.rs) before the window loads
+        .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
@@ -543,29 +879,61 @@ pub async fn run() {
         .plugin(tauri_plugin_notification::init())
         .manage(ImageCache(StdMutex::new(HashMap::new())))
         .manage(websocket_state.clone())
+        .manage(DownloadManager::default())
+        .manage(QueueScheduler::default())
+        .manage(ShutdownCoordinator::default())
         .setup(move |app| {
-            let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                let _ = fs::create_dir_all(app_handle.path().app_data_dir().unwrap());
-                let db_path = app_handle
-                    .path()
-                    .app_data_dir()
-                    .unwrap()
-                    .join("database.db");
-
-                let options = SqliteConnectOptions::new()
-                    .filename(db_path)
-                    .create_if_missing(true);
-                let pool = SqlitePool::connect_with(options).await;
-                match pool {
-                    Ok(db) => {
-                        app_handle.manage(StdMutex::new(db.clone()));
+            // Logged here, the log plugin isn't set up while the config is loaded
+            if let Some(e) = &startup_config_error {
+                error!("Failed to load the config: {}", e);
+            }
+            if env::var_os(PORT_ENV).is_some() && get_port_override().is_none() {
+                warn!("Ignoring {}, it is not a valid port", PORT_ENV);
+            }
+
+            if let Err(e) = save_launch_command() {
+                error!("Failed to record the launch command for the extension: {}", e);
+            }
+
+            // `--rollback-database <version>` only reverts the schema (before a downgrade) and exits
+            if let Some(version) = rollback_version {
+                let result = version
+                    .ok_or_else(|| {
+                        "--rollback-database needs the schema version to go back to".to_string()
+                    })
+                    .and_then(|version| database::rollback_database(app.handle(), version));
+                if let Err(e) = &result {
+                    error!("Database rollback failed: {}", e);
+                }
+                std::process::exit(if result.is_ok() { 0 } else { 1 });
+            }
+
+            let database = database::init_database(app.handle());
+            if let Ok(db) = database.pool() {
+                let app_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    // Clear PIDs left behind by a crash before anything can signal them
+                    match downloader::reconcile_stale_downloads(&db).await {
+                        Ok(0) => {}
+                        Ok(count) => info!("Marked {} stale downloads as interrupted", count),
+                        Err(e) => error!("Failed to reconcile stale downloads: {}", e),
                     }
-                    Err(e) => {
-                        eprintln!("Database connection error: {}", e);
+
+                    if import_settings {
+                        match import_settings_into_config(&app_handle, &db).await {
+                            Ok(()) => info!("Imported settings into the config"),
+                            Err(e) => error!("Failed to import settings into the config: {}", e),
+                        }
                     }
-                }
-            });
+
+                    // Bring back downloads that were paused by quitting the app
+                    if let Err(e) = queue::resume_downloads_paused_on_quit(&app_handle, &db).await {
+                        error!("Failed to resume downloads paused on quit: {}", e);
+                    }
+                    queue::notify_scheduler(&app_handle);
+                });
+            }
+            app.manage(database);
 
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)
                 .map_err(|e| format!("Failed to create quit menu item: {}", e))?;
@@ -585,14 +953,8 @@ pub async fn run() {
                         }
                     }
                     "quit" => {
-                        let app_handle = app.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let state_mutex = app_handle.state::<StdMutex<Pool<Sqlite>>>();
-                            if let Err(e) = pause_ongoing_downloads(state_mutex).await {
-                                println!("Error pausing downloads: {}", e);
-                            }
-                            app_handle.exit(0);
-                        });
+                        // Pauses ongoing downloads and waits for them before exiting
+                        tauri::async_runtime::spawn(shutdown::shutdown(app.clone(), 0));
                     }
                     _ => {}
                 })
@@ -634,10 +996,23 @@ pub async fn run() {
                 window.show().unwrap();
             }
 
+            // The scheduler runs independently of the webview, so the queue keeps
+            // moving even when started with --hidden or minimized to the tray
+            tauri::async_runtime::spawn(queue::run_scheduler(app.handle().clone()));
+
+            // Pick up edits made to the config file while the app runs
+            tauri::async_runtime::spawn(config_watcher::watch_config(app.handle().clone()));
+
+            // Pause downloads gracefully when the OS asks the app to terminate
+            tauri::async_runtime::spawn(shutdown::handle_termination_signals(app.handle().clone()));
+
             let websocket_app_handle = app.handle().clone();
             tokio::spawn(async move {
                 if let Err(e) = start_websocket_server(websocket_app_handle, port).await {
-                    println!("Failed to start initial WebSocket server: {}", e);
+                    error!(
+                        target: WEBSOCKET_LOG_TARGET,
+                        "Failed to start initial WebSocket server: {}", e
+                    );
                 }
             });
 
@@ -650,78 +1025,391 @@ pub async fn run() {
             open_link_with_app,
             list_ongoing_downloads,
             pause_ongoing_downloads,
+            suspend_download,
+            resume_suspended_download,
+            downloader::start_download,
+            downloader::stop_download,
+            downloader::list_active_downloads,
+            downloader::fetch_video_metadata,
+            queue::enqueue_download,
+            queue::process_download_queue,
+            database::list_database_backups,
+            database::restore_database_backup,
+            repository::db_get_download,
+            repository::db_list_downloads,
+            repository::db_save_download,
+            repository::db_update_download_status,
+            repository::db_delete_download,
+            repository::db_get_video_info,
+            repository::db_save_video_info,
+            repository::db_delete_video_info,
+            repository::db_get_playlist_info,
+            repository::db_save_playlist_info,
+            repository::db_delete_playlist_info,
+            repository::db_list_settings,
+            repository::db_save_setting,
+            repository::db_reset_settings,
+            repository::db_list_kv_pairs,
+            repository::db_save_kv_pair,
+            repository::db_delete_kv_pair,
             send_to_extension,
             receive_frontend_response,
             get_config,
-            update_config,
+            patch_config,
+            sync_config_setting,
             reset_config,
+            get_config_load_error,
             get_config_file_path,
             restart_websocket_server,
+            get_websocket_stats,
+            native_host::install_native_host,
+            native_host::verify_native_host,
+            native_host::uninstall_native_host,
             get_current_app_path,
             is_flatpak,
             get_appimage_path
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Covers every way of quitting (tray, closing the last window with quit-on-close, process plugin)
+            if let tauri::RunEvent::ExitRequested { code, api, .. } = event {
+                if !shutdown::is_shutdown_finished(app_handle) {
+                    api.prevent_exit();
+                    tauri::async_runtime::spawn(shutdown::shutdown(
+                        app_handle.clone(),
+                        code.unwrap_or(0),
+                    ));
+                }
+            }
+        });
+}
+
+async fn send_to_client(app_handle: &tauri::AppHandle, client_id: u64, message: String) {
+    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+    let sender = state
+        .lock()
+        .await
+        .clients
+        .get(&client_id)
+        .map(|client| client.sender.clone());
+    if let Some(sender) = sender {
+        let _ = sender
+            .lock()
+            .await
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                message.into(),
+            ))
+            .await;
+    }
+}
+
+async fn record_stat(app_handle: &tauri::AppHandle, update: impl FnOnce(&mut WebSocketStats)) {
+    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+    update(&mut state.lock().await.stats);
+}
+
+// Waits for the frontend's answer to one request and sends it back to the client that asked,
+// or an error reply once the request times out
+async fn forward_frontend_response(
+    app_handle: tauri::AppHandle,
+    client_id: u64,
+    request_id: u64,
+    id: Option<serde_json::Value>,
+    response_receiver: oneshot::Receiver<String>,
+) {
+    let response = match timeout(EXTENSION_REQUEST_TIMEOUT, response_receiver).await {
+        Ok(Ok(response)) => serde_json::from_str::<Response>(&response).unwrap_or_else(|e| {
+            error!(
+                target: WEBSOCKET_LOG_TARGET,
+                "Invalid response from the frontend to request {}: {}",
+                request_id,
+                e
+            );
+            Response::error(
+                ErrorCode::Internal,
+                format!("Invalid response from the app: {}", e),
+            )
+        }),
+        Ok(Err(_)) => Response::error(
+            ErrorCode::Internal,
+            "Request was dropped before the app responded",
+        ),
+        Err(_) => {
+            {
+                let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+                let mut state = state.lock().await;
+                state.pending_requests.remove(&request_id);
+                state.stats.timeouts += 1;
+            }
+            warn!(
+                target: WEBSOCKET_LOG_TARGET,
+                "Request {} from client {} timed out", request_id, client_id
+            );
+            Response::error(
+                ErrorCode::Timeout,
+                format!(
+                    "Timed out after {}s waiting for the app to respond",
+                    EXTENSION_REQUEST_TIMEOUT.as_secs()
+                ),
+            )
+        }
+    };
+
+    let frame = ResponseFrame::new(Some(request_id), response).with_id(id);
+    send_to_client(&app_handle, client_id, frame.to_json()).await;
+}
+
+// Refuses the WebSocket upgrade for web pages, see `extension::is_allowed_origin`
+fn check_origin(
+    request: &HandshakeRequest,
+    response: HandshakeResponse,
+) -> Result<HandshakeResponse, ErrorResponse> {
+    let origin = request
+        .headers()
+        .get("origin")
+        .map(|origin| origin.to_str().unwrap_or_default());
+    if extension::is_allowed_origin(origin) {
+        return Ok(response);
+    }
+    warn!(
+        target: WEBSOCKET_LOG_TARGET,
+        "Rejected connection from origin {:?}", origin
+    );
+    let mut response = ErrorResponse::new(Some("Origin not allowed".to_string()));
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    Err(response)
 }
 
 async fn handle_connection(stream: TcpStream, app_handle: tauri::AppHandle) {
-    let ws_stream = accept_async(stream).await.unwrap();
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    // Port scanners and plain HTTP requests end up here, nothing to reply to
+    let ws_stream = match accept_hdr_async(stream, check_origin).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            let rejected_origin = matches!(
+                &e,
+                WebSocketError::Http(response) if response.status() == StatusCode::FORBIDDEN
+            );
+            record_stat(&app_handle, |stats| {
+                if rejected_origin {
+                    stats.rejected_origins += 1;
+                } else {
+                    stats.handshake_failures += 1;
+                }
+            })
+            .await;
+            if !rejected_origin {
+                warn!(
+                    target: WEBSOCKET_LOG_TARGET,
+                    "Handshake with {} failed: {}", peer, e
+                );
+            }
+            return;
+        }
+    };
     let (ws_sender, mut ws_receiver) = ws_stream.split();
 
-    // Store the sender in the shared state
-    {
+    // Register the client, every connection gets its own sender
+    let (client_id, auth_token) = {
         let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
         let mut state = state.lock().await;
-        state.sender = Some(ws_sender);
-    }
+        let client_id = state.next_client_id;
+        state.next_client_id += 1;
+        state.stats.connections += 1;
+        state.clients.insert(
+            client_id,
+            WebSocketClient {
+                sender: Arc::new(Mutex::new(ws_sender)),
+                subscription: Default::default(),
+                authenticated: false,
+            },
+        );
+        (client_id, state.auth_token.clone())
+    };
+    let mut authenticated = false;
+    let hello_deadline = tokio::time::Instant::now() + HELLO_TIMEOUT;
+
+    info!(
+        target: WEBSOCKET_LOG_TARGET,
+        "Client {} connected from {}", client_id, peer
+    );
+
+    loop {
+        // A client that never authenticates must not keep its connection open
+        let msg = if authenticated {
+            ws_receiver.next().await
+        } else {
+            match timeout_at(hello_deadline, ws_receiver.next()).await {
+                Ok(msg) => msg,
+                Err(_) => {
+                    warn!(
+                        target: WEBSOCKET_LOG_TARGET,
+                        "Closing connection to client {}: no hello within {:?}",
+                        client_id,
+                        HELLO_TIMEOUT
+                    );
+                    record_stat(&app_handle, |stats| stats.unauthorized += 1).await;
+                    break;
+                }
+            }
+        };
+        let Some(msg) = msg else { break };
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!(
+                    target: WEBSOCKET_LOG_TARGET,
+                    "Connection to client {} failed: {}", client_id, e
+                );
+                break;
+            }
+        };
+        if !msg.is_text() {
+            continue;
+        }
+        let Ok(text) = msg.to_text() else { continue };
+
+        // Malformed messages and unknown commands get an error reply without a request ID
+        let RequestFrame { id, request } = match neodlp_protocol::parse_request(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!(
+                    target: WEBSOCKET_LOG_TARGET,
+                    "Invalid message from client {}: {}", client_id, e
+                );
+                record_stat(&app_handle, |stats| stats.invalid_messages += 1).await;
+                let frame = ResponseFrame::new(None, e.into())
+                    .with_id(neodlp_protocol::parse_request_id(text));
+                send_to_client(&app_handle, client_id, frame.to_json()).await;
+                continue;
+            }
+        };
+
+        // Nothing is handled before a `hello` with the right token, anything else disconnects
+        if !authenticated {
+            let rejection = match &request {
+                Request::Hello { token, .. } => {
+                    (!extension::is_authorized(token.as_deref(), &auth_token))
+                        .then_some("Invalid authentication token")
+                }
+                _ => Some("The first message must be a hello with the authentication token"),
+            };
+            if let Some(message) = rejection {
+                warn!(
+                    target: WEBSOCKET_LOG_TARGET,
+                    "Closing connection to client {}: {}", client_id, message
+                );
+                record_stat(&app_handle, |stats| stats.unauthorized += 1).await;
+                let frame =
+                    ResponseFrame::new(None, Response::error(ErrorCode::Unauthorized, message))
+                        .with_id(id);
+                send_to_client(&app_handle, client_id, frame.to_json()).await;
+                break;
+            }
+            authenticated = true;
+            let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+            if let Some(client) = state.lock().await.clients.get_mut(&client_id) {
+                client.authenticated = true;
+            }
+        }
 
-    println!("New WebSocket connection established");
+        let request_id = {
+            let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+            let mut state = state.lock().await;
+            let request_id = state.next_request_id;
+            state.next_request_id += 1;
+            request_id
+        };
+        info!(
+            target: WEBSOCKET_LOG_TARGET,
+            "Request {} from client {}: {}",
+            request_id,
+            client_id,
+            request.command()
+        );
 
-    while let Some(msg) = ws_receiver.next().await {
-        if let Ok(msg) = msg {
-            if let Ok(text) = msg.to_text() {
-                println!("Received message: {}", text);
+        if let Some(response) = extension::handle_request(&app_handle, client_id, &request).await {
+            let incompatible = matches!(
+                (&request, &response),
+                (Request::Hello { .. }, Response::Error { .. })
+            );
+            let frame = ResponseFrame::new(Some(request_id), response).with_id(id);
+            send_to_client(&app_handle, client_id, frame.to_json()).await;
+            if incompatible {
+                warn!(
+                    target: WEBSOCKET_LOG_TARGET,
+                    "Closing connection to client {}: unsupported protocol version", client_id
+                );
+                break;
+            }
+            continue;
+        }
 
-                // Parse the JSON message
-                if let Ok(json_value) = serde_json::from_str::<Value>(text) {
-                    // Create a new channel for this request
-                    let (response_sender, response_receiver) = oneshot::channel();
-                    {
-                        let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
-                        let mut state = state.lock().await;
-                        state.response_channel.sender = Some(response_sender);
-                    }
+        // Register a pending request, its ID routes the response back to this client
+        let (response_sender, response_receiver) = oneshot::channel();
+        {
+            let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+            let mut state = state.lock().await;
+            state.pending_requests.insert(
+                request_id,
+                PendingRequest {
+                    client_id,
+                    sender: response_sender,
+                },
+            );
+        }
 
-                    // Emit an event to the frontend
-                    app_handle
-                        .emit_to("main", "websocket-message", json_value)
-                        .unwrap();
-
-                    // Wait for the response from the frontend
-                    let response = response_receiver
-                        .await
-                        .unwrap_or_else(|e| format!("Error receiving response: {:?}", e));
-
-                    // Send the response back through WebSocket
-                    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
-                    let mut state = state.lock().await;
-                    if let Some(sender) = &mut state.sender {
-                        let _ = sender
-                            .send(tokio_tungstenite::tungstenite::Message::Text(
-                                response.into(),
-                            ))
-                            .await;
-                    }
-                }
+        // Emit an event to the frontend, if that fails nobody is going to answer
+        let event = ExtensionRequestEvent {
+            client_id,
+            request_id,
+            request,
+        };
+        if let Err(e) = app_handle.emit_to("main", "websocket-message", event) {
+            error!(
+                target: WEBSOCKET_LOG_TARGET,
+                "Failed to forward request {} to the frontend: {}", request_id, e
+            );
+            {
+                let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+                let mut state = state.lock().await;
+                state.pending_requests.remove(&request_id);
+                state.stats.emit_failures += 1;
             }
+            let response = Response::error(
+                ErrorCode::Internal,
+                format!("Failed to forward the request to the app: {}", e),
+            );
+            let frame = ResponseFrame::new(Some(request_id), response).with_id(id);
+            send_to_client(&app_handle, client_id, frame.to_json()).await;
+            continue;
         }
+
+        // Don't block this connection, overlapping requests are answered independently
+        tokio::spawn(forward_frontend_response(
+            app_handle.clone(),
+            client_id,
+            request_id,
+            id,
+            response_receiver,
+        ));
     }
 
-    println!("WebSocket connection closed");
+    info!(
+        target: WEBSOCKET_LOG_TARGET,
+        "Client {} disconnected", client_id
+    );
 
-    // Remove the sender from the shared state when the connection closes
+    // Remove only this client (and the requests it is still waiting for), others stay connected
     let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
     let mut state = state.lock().await;
-    state.sender = None;
+    state.clients.remove(&client_id);
+    state
+        .pending_requests
+        .retain(|_, request| request.client_id != client_id);
 }
@@ -237,5 +237,191 @@ pub fn get_migrations() -> Vec<Migration> {
             CREATE INDEX IF NOT EXISTS idx_downloads_id_desc ON downloads(id DESC);
         ",
         kind: MigrationKind::Up,
+    },
+    Migration {
+        version: 4,
+        description: "add_download_args_and_queue_index_to_downloads",
+        sql: "
+            -- yt-dlp arguments (JSON array) used by the backend to start or resume a download
+            ALTER TABLE downloads ADD COLUMN download_args TEXT;
+
+            -- Speed up picking the next queued download
+            CREATE INDEX IF NOT EXISTS idx_downloads_status_queue ON downloads(download_status, queue_index);
+        ",
+        kind: MigrationKind::Up,
+    },
+    Migration {
+        version: 5,
+        description: "add_pause_reason_to_downloads",
+        sql: "
+            -- Why a download was paused: 'quit' (paused by the app on exit) or NULL/'user'
+            ALTER TABLE downloads ADD COLUMN pause_reason TEXT;
+
+            -- Forget the pause reason as soon as a download leaves the paused state
+            CREATE TRIGGER IF NOT EXISTS clear_downloads_pause_reason
+                AFTER UPDATE OF download_status ON downloads
+                FOR EACH ROW
+                WHEN NEW.download_status != 'paused' AND NEW.pause_reason IS NOT NULL
+            BEGIN
+                UPDATE downloads SET pause_reason = NULL WHERE id = NEW.id;
+            END;
+        ",
+        kind: MigrationKind::Up,
+    },
+    // Down migrations, each one reverts the Up migration with the same version
+    Migration {
+        version: 1,
+        description: "create_initial_tables",
+        sql: "
+            DROP TABLE IF EXISTS downloads;
+            DROP TABLE IF EXISTS playlist_info;
+            DROP TABLE IF EXISTS video_info;
+            DROP TABLE IF EXISTS settings;
+            DROP TABLE IF EXISTS kv_store;
+        ",
+        kind: MigrationKind::Down,
+    },
+    Migration {
+        version: 2,
+        description: "add_columns_to_downloads",
+        sql: "
+            -- Recreate the version 1 table without the columns added in version 2
+            CREATE TABLE downloads_temp (
+                id INTEGER PRIMARY KEY NOT NULL,
+                download_id TEXT UNIQUE NOT NULL,
+                download_status TEXT NOT NULL,
+                video_id TEXT NOT NULL,
+                format_id TEXT NOT NULL,
+                subtitle_id TEXT,
+                queue_index INTEGER,
+                playlist_id TEXT,
+                playlist_index INTEGER,
+                resolution TEXT,
+                ext TEXT,
+                abr REAL,
+                vbr REAL,
+                acodec TEXT,
+                vcodec TEXT,
+                dynamic_range TEXT,
+                process_id INTEGER,
+                status TEXT,
+                progress REAL,
+                total INTEGER,
+                downloaded INTEGER,
+                speed REAL,
+                eta INTEGER,
+                filepath TEXT,
+                filetype TEXT,
+                filesize INTEGER,
+                FOREIGN KEY (video_id) REFERENCES video_info (video_id),
+                FOREIGN KEY (playlist_id) REFERENCES playlist_info (playlist_id)
+            );
+
+            INSERT INTO downloads_temp SELECT
+                id, download_id, download_status, video_id, format_id, subtitle_id,
+                queue_index, playlist_id, playlist_index, resolution, ext, abr, vbr,
+                acodec, vcodec, dynamic_range, process_id, status, progress, total,
+                downloaded, speed, eta, filepath, filetype, filesize
+            FROM downloads;
+
+            DROP TRIGGER IF EXISTS update_downloads_updated_at;
+            DROP TABLE downloads;
+            ALTER TABLE downloads_temp RENAME TO downloads;
+        ",
+        kind: MigrationKind::Down,
+    },
+    Migration {
+        version: 3,
+        description: "add_more_columns_and_indices_to_downloads",
+        sql: "
+            -- Recreate the version 2 table without the columns added in version 3
+            CREATE TABLE downloads_temp (
+                id INTEGER PRIMARY KEY NOT NULL,
+                download_id TEXT UNIQUE NOT NULL,
+                download_status TEXT NOT NULL,
+                video_id TEXT NOT NULL,
+                format_id TEXT NOT NULL,
+                subtitle_id TEXT,
+                queue_index INTEGER,
+                playlist_id TEXT,
+                playlist_index INTEGER,
+                resolution TEXT,
+                ext TEXT,
+                abr REAL,
+                vbr REAL,
+                acodec TEXT,
+                vcodec TEXT,
+                dynamic_range TEXT,
+                process_id INTEGER,
+                status TEXT,
+                progress REAL,
+                total INTEGER,
+                downloaded INTEGER,
+                speed REAL,
+                eta INTEGER,
+                filepath TEXT,
+                filetype TEXT,
+                filesize INTEGER,
+                output_format TEXT,
+                embed_metadata INTEGER NOT NULL DEFAULT 0,
+                embed_thumbnail INTEGER NOT NULL DEFAULT 0,
+                sponsorblock_remove TEXT,
+                sponsorblock_mark TEXT,
+                use_aria2 INTEGER NOT NULL DEFAULT 0,
+                custom_command TEXT,
+                queue_config TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (video_id) REFERENCES video_info (video_id),
+                FOREIGN KEY (playlist_id) REFERENCES playlist_info (playlist_id)
+            );
+
+            INSERT INTO downloads_temp SELECT
+                id, download_id, download_status, video_id, format_id, subtitle_id,
+                queue_index, playlist_id,
+                CAST(playlist_indices AS INTEGER),                            -- Keeps the first index of TEXT playlist_indices
+                resolution, ext, abr, vbr,
+                acodec, vcodec, dynamic_range, process_id, status,
+                progress, total, downloaded, speed, eta,
+                filepath, filetype, filesize,
+                output_format, embed_metadata, embed_thumbnail,
+                sponsorblock_remove, sponsorblock_mark, use_aria2,
+                custom_command, queue_config, created_at, updated_at
+            FROM downloads;
+
+            DROP TRIGGER IF EXISTS update_downloads_updated_at;
+            DROP INDEX IF EXISTS idx_downloads_video_id;
+            DROP INDEX IF EXISTS idx_downloads_playlist_id;
+            DROP INDEX IF EXISTS idx_downloads_status_updated;
+            DROP INDEX IF EXISTS idx_downloads_id_desc;
+            DROP TABLE downloads;
+            ALTER TABLE downloads_temp RENAME TO downloads;
+
+            CREATE TRIGGER IF NOT EXISTS update_downloads_updated_at
+                AFTER UPDATE ON downloads
+                FOR EACH ROW
+            BEGIN
+                UPDATE downloads SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+            END;
+        ",
+        kind: MigrationKind::Down,
+    },
+    Migration {
+        version: 4,
+        description: "add_download_args_and_queue_index_to_downloads",
+        sql: "
+            DROP INDEX IF EXISTS idx_downloads_status_queue;
+            ALTER TABLE downloads DROP COLUMN download_args;
+        ",
+        kind: MigrationKind::Down,
+    },
+    Migration {
+        version: 5,
+        description: "add_pause_reason_to_downloads",
+        sql: "
+            DROP TRIGGER IF EXISTS clear_downloads_pause_reason;
+            ALTER TABLE downloads DROP COLUMN pause_reason;
+        ",
+        kind: MigrationKind::Down,
     }]
 }
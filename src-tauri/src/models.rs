@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Starting,
+    Downloading,
+    Paused,
+    Suspended,
+    Interrupted,
+    Completed,
+    Errored,
+}
+
+impl DownloadStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DownloadStatus::Queued => "queued",
+            DownloadStatus::Starting => "starting",
+            DownloadStatus::Downloading => "downloading",
+            DownloadStatus::Paused => "paused",
+            DownloadStatus::Suspended => "suspended",
+            DownloadStatus::Interrupted => "interrupted",
+            DownloadStatus::Completed => "completed",
+            DownloadStatus::Errored => "errored",
+        }
+    }
+}
+
+impl fmt::Display for DownloadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for DownloadStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "queued" => Ok(DownloadStatus::Queued),
+            "starting" => Ok(DownloadStatus::Starting),
+            "downloading" => Ok(DownloadStatus::Downloading),
+            "paused" => Ok(DownloadStatus::Paused),
+            "suspended" => Ok(DownloadStatus::Suspended),
+            "interrupted" => Ok(DownloadStatus::Interrupted),
+            "completed" => Ok(DownloadStatus::Completed),
+            "errored" => Ok(DownloadStatus::Errored),
+            _ => Err(format!("Unknown download status: {}", value)),
+        }
+    }
+}
+
+/// A row of the `downloads` table. `id`, `created_at` and `updated_at` are
+/// filled in by the database and may be omitted when saving.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Download {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub download_id: String,
+    #[sqlx(try_from = "String")]
+    pub download_status: DownloadStatus,
+    pub video_id: String,
+    pub format_id: String,
+    pub subtitle_id: Option<String>,
+    pub queue_index: Option<i64>,
+    pub playlist_id: Option<String>,
+    pub playlist_indices: Option<String>,
+    pub resolution: Option<String>,
+    pub ext: Option<String>,
+    pub abr: Option<f64>,
+    pub vbr: Option<f64>,
+    pub acodec: Option<String>,
+    pub vcodec: Option<String>,
+    pub dynamic_range: Option<String>,
+    pub process_id: Option<i64>,
+    pub status: Option<String>,
+    pub item: Option<String>,
+    pub progress: Option<f64>,
+    pub total: Option<i64>,
+    pub downloaded: Option<i64>,
+    pub speed: Option<f64>,
+    pub eta: Option<i64>,
+    pub filepath: Option<String>,
+    pub filetype: Option<String>,
+    pub filesize: Option<i64>,
+    pub output_format: Option<String>,
+    pub embed_metadata: bool,
+    pub embed_thumbnail: bool,
+    pub square_crop_thumbnail: bool,
+    pub sponsorblock_remove: Option<String>,
+    pub sponsorblock_mark: Option<String>,
+    pub use_aria2: bool,
+    pub custom_command: Option<String>,
+    pub queue_config: Option<String>,
+    #[serde(default)]
+    pub download_args: Option<String>,
+    #[serde(default)]
+    pub pause_reason: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// A download joined with its video and (optional) playlist info, the shape the frontend lists.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DownloadDetails {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub download: Download,
+    pub title: String,
+    pub url: String,
+    pub host: String,
+    pub thumbnail: Option<String>,
+    pub channel: Option<String>,
+    pub duration_string: Option<String>,
+    pub release_date: Option<String>,
+    pub view_count: Option<i64>,
+    pub like_count: Option<i64>,
+    pub playlist_title: Option<String>,
+    pub playlist_url: Option<String>,
+    pub playlist_n_entries: Option<i64>,
+    pub playlist_channel: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VideoInfo {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub video_id: String,
+    pub title: String,
+    pub url: String,
+    pub host: String,
+    pub thumbnail: Option<String>,
+    pub channel: Option<String>,
+    pub duration_string: Option<String>,
+    pub release_date: Option<String>,
+    pub view_count: Option<i64>,
+    pub like_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PlaylistInfo {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub playlist_id: String,
+    pub playlist_title: String,
+    pub playlist_url: String,
+    pub playlist_n_entries: i64,
+    pub playlist_channel: Option<String>,
+}
+
+/// A `settings` or `kv_store` entry, stored in the database as `{"value": ...}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyValue {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DownloadFilter {
+    pub statuses: Vec<DownloadStatus>,
+    pub video_id: Option<String>,
+    pub playlist_id: Option<String>,
+    /// Case-insensitive match against the video title and channel
+    pub search: Option<String>,
+    /// 1-based page number
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
@@ -0,0 +1,352 @@
+use crate::config::get_config_dir;
+use directories::BaseDirs;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HOST_NAME: &str = "com.neosubhamoy.neodlp";
+const HOST_DESCRIPTION: &str = "NeoDLP MsgHost";
+const HOST_BINARY: &str = "neodlp-msghost";
+const CHROME_EXTENSION_ORIGIN: &str = "chrome-extension://mehopeailfjmiloiiohgicphlcgpompf/";
+const FIREFOX_EXTENSION_ID: &str = "neodlp@neosubhamoy.com";
+// Everything written by `install`, so `uninstall` removes exactly that
+const INSTALL_RECORD_FILE: &str = "native-hosts.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ManifestKind {
+    Chromium,
+    Firefox,
+}
+
+struct Browser {
+    name: &'static str,
+    kind: ManifestKind,
+    // Relative to the home dir for Firefox and to the config dir for Chromium based browsers
+    profile_dir: &'static str,
+    flatpak_id: &'static str,
+}
+
+const BROWSERS: &[Browser] = &[
+    Browser {
+        name: "Firefox",
+        kind: ManifestKind::Firefox,
+        profile_dir: ".mozilla",
+        flatpak_id: "org.mozilla.firefox",
+    },
+    Browser {
+        name: "Chrome",
+        kind: ManifestKind::Chromium,
+        profile_dir: "google-chrome",
+        flatpak_id: "com.google.Chrome",
+    },
+    Browser {
+        name: "Chromium",
+        kind: ManifestKind::Chromium,
+        profile_dir: "chromium",
+        flatpak_id: "org.chromium.Chromium",
+    },
+    Browser {
+        name: "Brave",
+        kind: ManifestKind::Chromium,
+        profile_dir: "BraveSoftware/Brave-Browser",
+        flatpak_id: "com.brave.Browser",
+    },
+    Browser {
+        name: "Vivaldi",
+        kind: ManifestKind::Chromium,
+        profile_dir: "vivaldi",
+        flatpak_id: "com.vivaldi.Vivaldi",
+    },
+    Browser {
+        name: "Edge",
+        kind: ManifestKind::Chromium,
+        profile_dir: "microsoft-edge",
+        flatpak_id: "com.microsoft.Edge",
+    },
+];
+
+/// Where one browser (native or Flatpak) looks for our manifest.
+struct Target {
+    browser: String,
+    kind: ManifestKind,
+    // Only exists once the browser has been run, nothing is installed otherwise
+    profile_dir: PathBuf,
+    manifest_path: PathBuf,
+    // Flatpak browsers can't run host binaries directly, they go through this script
+    wrapper_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestState {
+    Installed,
+    Missing,
+    Outdated,
+    Invalid,
+    BrowserNotFound,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ManifestStatus {
+    pub browser: String,
+    pub path: String,
+    pub state: ManifestState,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct InstallRecord {
+    paths: Vec<PathBuf>,
+}
+
+fn manifest_dir_name(kind: ManifestKind) -> &'static str {
+    match kind {
+        ManifestKind::Firefox => "native-messaging-hosts",
+        ManifestKind::Chromium => "NativeMessagingHosts",
+    }
+}
+
+fn get_targets(home_dir: &Path, config_dir: &Path) -> Vec<Target> {
+    let manifest_file = format!("{}.json", HOST_NAME);
+    let mut targets = Vec::new();
+
+    for browser in BROWSERS {
+        let native_root = match browser.kind {
+            ManifestKind::Firefox => home_dir,
+            ManifestKind::Chromium => config_dir,
+        };
+        let profile_dir = native_root.join(browser.profile_dir);
+        targets.push(Target {
+            browser: browser.name.to_string(),
+            kind: browser.kind,
+            manifest_path: profile_dir
+                .join(manifest_dir_name(browser.kind))
+                .join(&manifest_file),
+            profile_dir,
+            wrapper_path: None,
+        });
+
+        // Inside the sandbox `$HOME` stays the same but `$XDG_CONFIG_HOME` is `~/.var/app/<id>/config`
+        let app_dir = home_dir.join(".var/app").join(browser.flatpak_id);
+        let sandbox_root = match browser.kind {
+            ManifestKind::Firefox => app_dir.clone(),
+            ManifestKind::Chromium => app_dir.join("config"),
+        };
+        let profile_dir = sandbox_root.join(browser.profile_dir);
+        targets.push(Target {
+            browser: format!("{} (Flatpak)", browser.name),
+            kind: browser.kind,
+            manifest_path: profile_dir
+                .join(manifest_dir_name(browser.kind))
+                .join(&manifest_file),
+            profile_dir,
+            wrapper_path: Some(app_dir.join("data/neodlp").join(HOST_BINARY)),
+        });
+    }
+
+    targets
+}
+
+fn get_user_targets() -> Result<Vec<Target>, String> {
+    let base_dirs =
+        BaseDirs::new().ok_or_else(|| "Could not determine home directory".to_string())?;
+    Ok(get_targets(base_dirs.home_dir(), base_dirs.config_dir()))
+}
+
+fn build_manifest(kind: ManifestKind, path: &Path) -> Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": HOST_DESCRIPTION,
+        "path": path.to_string_lossy(),
+        "type": "stdio",
+    });
+    match kind {
+        ManifestKind::Chromium => manifest["allowed_origins"] = json!([CHROME_EXTENSION_ORIGIN]),
+        ManifestKind::Firefox => manifest["allowed_extensions"] = json!([FIREFOX_EXTENSION_ID]),
+    }
+    manifest
+}
+
+// Needs the browser to be allowed to talk to `org.freedesktop.Flatpak`
+fn build_wrapper(host_path: &Path) -> String {
+    format!(
+        "#!/bin/sh\nexec flatpak-spawn --host '{}' \"$@\"\n",
+        host_path.to_string_lossy().replace('\'', r"'\''")
+    )
+}
+
+fn write_executable(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn get_record_path() -> Result<PathBuf, String> {
+    get_config_dir()
+        .map(|dir| dir.join(INSTALL_RECORD_FILE))
+        .ok_or_else(|| "Could not determine config directory".to_string())
+}
+
+fn load_record() -> InstallRecord {
+    get_record_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_record(record: &InstallRecord) -> Result<(), String> {
+    let record_path = get_record_path()?;
+    if let Some(dir) = record_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(record)
+        .map_err(|e| format!("Failed to serialize install record: {}", e))?;
+    fs::write(record_path, content).map_err(|e| format!("Failed to write install record: {}", e))
+}
+
+fn check_target(target: &Target, host_path: &Path) -> ManifestState {
+    if !target.profile_dir.is_dir() {
+        return ManifestState::BrowserNotFound;
+    }
+    let Ok(content) = fs::read_to_string(&target.manifest_path) else {
+        return ManifestState::Missing;
+    };
+    let Ok(manifest) = serde_json::from_str::<Value>(&content) else {
+        return ManifestState::Invalid;
+    };
+
+    let executable = target.wrapper_path.as_deref().unwrap_or(host_path);
+    if manifest != build_manifest(target.kind, executable) {
+        return ManifestState::Outdated;
+    }
+    if let Some(wrapper_path) = &target.wrapper_path {
+        if fs::read_to_string(wrapper_path).ok() != Some(build_wrapper(host_path)) {
+            return ManifestState::Outdated;
+        }
+    }
+    ManifestState::Installed
+}
+
+fn verify(targets: &[Target], host_path: &Path) -> Vec<ManifestStatus> {
+    targets
+        .iter()
+        .map(|target| ManifestStatus {
+            browser: target.browser.clone(),
+            path: target.manifest_path.to_string_lossy().into_owned(),
+            state: check_target(target, host_path),
+        })
+        .collect()
+}
+
+/// Writes the manifest of every browser that has a profile, pointing at `host_path`.
+fn install(targets: &[Target], host_path: &Path) -> Result<Vec<ManifestStatus>, String> {
+    let mut record = load_record();
+
+    for target in targets.iter().filter(|target| target.profile_dir.is_dir()) {
+        let executable = match &target.wrapper_path {
+            Some(wrapper_path) => {
+                if let Some(dir) = wrapper_path.parent() {
+                    fs::create_dir_all(dir)
+                        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+                }
+                write_executable(wrapper_path, &build_wrapper(host_path))?;
+                record.paths.push(wrapper_path.clone());
+                wrapper_path.as_path()
+            }
+            None => host_path,
+        };
+
+        if let Some(dir) = target.manifest_path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(&build_manifest(target.kind, executable))
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+        fs::write(&target.manifest_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", target.manifest_path.display(), e))?;
+        record.paths.push(target.manifest_path.clone());
+        info!(
+            "Installed native messaging host manifest for {}",
+            target.browser
+        );
+    }
+
+    record.paths.sort();
+    record.paths.dedup();
+    save_record(&record)?;
+    Ok(verify(targets, host_path))
+}
+
+/// Removes everything `install` wrote, plus our manifests at the known locations.
+fn uninstall(targets: &[Target]) -> Result<Vec<String>, String> {
+    let mut paths = load_record().paths;
+    for target in targets {
+        paths.push(target.manifest_path.clone());
+        paths.extend(target.wrapper_path.clone());
+    }
+    paths.sort();
+    paths.dedup();
+
+    let mut removed = Vec::new();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        match fs::remove_file(path) {
+            Ok(()) => removed.push(path.to_string_lossy().into_owned()),
+            Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+
+    let record_path = get_record_path()?;
+    if record_path.exists() {
+        fs::remove_file(&record_path)
+            .map_err(|e| format!("Failed to remove install record: {}", e))?;
+    }
+    info!("Removed {} native messaging host files", removed.len());
+    Ok(removed)
+}
+
+// Installed next to the app binary by the deb and rpm packages
+fn get_host_path() -> Result<PathBuf, String> {
+    if !cfg!(target_os = "linux") {
+        return Err(
+            "Native messaging host is registered by the installer on this platform".to_string(),
+        );
+    }
+    if std::env::var("FLATPAK").is_ok() {
+        return Err("Native messaging host is not available inside Flatpak".to_string());
+    }
+    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    let host_path = exe_path
+        .parent()
+        .ok_or("Failed to get parent directory")?
+        .join(HOST_BINARY);
+    if !host_path.is_file() {
+        return Err(format!(
+            "{} not found at {}",
+            HOST_BINARY,
+            host_path.display()
+        ));
+    }
+    Ok(host_path)
+}
+
+#[tauri::command]
+pub fn install_native_host() -> Result<Vec<ManifestStatus>, String> {
+    install(&get_user_targets()?, &get_host_path()?)
+}
+
+#[tauri::command]
+pub fn verify_native_host() -> Result<Vec<ManifestStatus>, String> {
+    Ok(verify(&get_user_targets()?, &get_host_path()?))
+}
+
+#[tauri::command]
+pub fn uninstall_native_host() -> Result<Vec<String>, String> {
+    uninstall(&get_user_targets()?)
+}
@@ -0,0 +1,239 @@
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+// Interval used while polling for a process to exit
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(unix)]
+pub fn send_signal(pid: i32, signal: libc::c_int) -> Result<(), String> {
+    // kill() with a PID <= 0 targets whole process groups, never allow that by accident
+    if pid <= 0 {
+        return Err(format!("Refusing to signal invalid PID: {}", pid));
+    }
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to send signal {} to PID {}: {}",
+            signal,
+            pid,
+            std::io::Error::last_os_error()
+        ))
+    }
+}
+
+// Reads the parent PID from /proc/<pid>/stat, the field right after the process state
+#[cfg(target_os = "linux")]
+fn read_parent_pid(pid: i32) -> Option<i32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Returns all descendants of a process (children, grandchildren, ...) by walking /proc.
+#[cfg(target_os = "linux")]
+pub fn list_descendants(pid: i32) -> Vec<i32> {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    if let Ok(entries) = std::fs::read_dir("/proc") {
+        for entry in entries.flatten() {
+            let Some(child) = entry.file_name().to_str().and_then(|name| name.parse().ok()) else {
+                continue;
+            };
+            if let Some(parent) = read_parent_pid(child) {
+                children.entry(parent).or_default().push(child);
+            }
+        }
+    }
+
+    let mut descendants = Vec::new();
+    let mut pending = vec![pid];
+    while let Some(parent) = pending.pop() {
+        if let Some(direct_children) = children.get(&parent) {
+            for &child in direct_children {
+                if child != pid && !descendants.contains(&child) {
+                    descendants.push(child);
+                    pending.push(child);
+                }
+            }
+        }
+    }
+    descendants
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn list_descendants(_pid: i32) -> Vec<i32> {
+    // Without /proc only the process group can be used to reach the children
+    Vec::new()
+}
+
+#[cfg(unix)]
+fn list_group_members(pgid: i32) -> Vec<i32> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_dir("/proc")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok()))
+                    .filter(|&pid| unsafe { libc::getpgid(pid) } == pgid)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        vec![pgid]
+    }
+}
+
+/// Sends a signal to a process and its whole tree: the process group it leads
+/// (children spawned by the backend get their own group) plus every descendant
+/// found through /proc, including those that moved to another group.
+/// Returns the PIDs that were successfully signaled.
+#[cfg(unix)]
+pub fn signal_process_tree(pid: i32, signal: libc::c_int) -> Result<Vec<i32>, String> {
+    if pid <= 0 || pid == std::process::id() as i32 {
+        return Err(format!("Refusing to signal invalid PID: {}", pid));
+    }
+
+    // Collect the tree before signaling, children get re-parented once their parent exits
+    let group = if unsafe { libc::getpgid(pid) } == pid {
+        list_group_members(pid)
+    } else {
+        Vec::new()
+    };
+    let descendants = list_descendants(pid);
+
+    let mut signaled = Vec::new();
+    if !group.is_empty() && unsafe { libc::killpg(pid, signal) } == 0 {
+        signaled.extend(group);
+    }
+
+    // Descendants that left the group (or all of them if it isn't a group leader)
+    for target in std::iter::once(pid).chain(descendants) {
+        if !signaled.contains(&target) && send_signal(target, signal).is_ok() {
+            signaled.push(target);
+        }
+    }
+
+    if signaled.is_empty() {
+        return Err(format!("No process could be signaled in the tree of PID {}", pid));
+    }
+    Ok(signaled)
+}
+
+#[cfg(target_os = "linux")]
+fn is_zombie(pid: i32) -> bool {
+    // The state is the first field after the parenthesized command name in /proc/<pid>/stat
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| {
+            stat.rsplit_once(')')
+                .and_then(|(_, rest)| rest.split_whitespace().next().map(|state| state == "Z"))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+pub fn is_process_running(pid: i32) -> bool {
+    if pid <= 0 {
+        return false;
+    }
+    let alive = unsafe { libc::kill(pid, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+
+    // An exited but not yet reaped child still answers to kill(pid, 0)
+    #[cfg(target_os = "linux")]
+    if alive && is_zombie(pid) {
+        return false;
+    }
+
+    alive
+}
+
+#[cfg(windows)]
+pub fn is_process_running(pid: i32) -> bool {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    if pid <= 0 {
+        return false;
+    }
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(false)
+}
+
+// Process start time as unix timestamp, from the `starttime` field of /proc/<pid>/stat
+// (clock ticks since boot) and the boot time (`btime`) from /proc/stat
+#[cfg(target_os = "linux")]
+fn read_process_start_time(pid: i32) -> Option<i64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, rest) = stat.rsplit_once(')')?;
+    // Fields after the command name start at field 3 (state), starttime is field 22
+    let start_ticks: i64 = rest.split_whitespace().nth(19)?.parse().ok()?;
+
+    let boot_time: i64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return None;
+    }
+    Some(boot_time + start_ticks / ticks_per_second as i64)
+}
+
+/// Checks that a stored PID still belongs to a yt-dlp process started no later
+/// than `started_before` (unix timestamp), so a recycled PID is never signaled.
+#[cfg(target_os = "linux")]
+pub fn is_ytdlp_process(pid: i32, started_before: Option<i64>) -> bool {
+    if !is_process_running(pid) {
+        return false;
+    }
+
+    // Either yt-dlp itself or the `sh -c '.../yt-dlp ...'` wrapper used on Flatpak
+    let is_ytdlp = std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|cmdline| {
+            cmdline
+                .split(|byte| *byte == 0)
+                .take(3)
+                .any(|arg| String::from_utf8_lossy(arg).contains("yt-dlp"))
+        })
+        .unwrap_or(false);
+    if !is_ytdlp {
+        return false;
+    }
+
+    match (started_before, read_process_start_time(pid)) {
+        (Some(started_before), Some(started_at)) => started_at <= started_before,
+        _ => true,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_ytdlp_process(pid: i32, _started_before: Option<i64>) -> bool {
+    is_process_running(pid)
+}
+
+/// Polls until the process exits, returns `false` if it is still running after `timeout`.
+pub async fn wait_for_exit(pid: i32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_process_running(pid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        sleep(EXIT_POLL_INTERVAL).await;
+    }
+    true
+}
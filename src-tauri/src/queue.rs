@@ -0,0 +1,246 @@
+use crate::{
+    config::DownloadConfig, database::get_pool, downloader::spawn_download, extension,
+    shutdown::is_shutting_down, WebSocketState, PAUSE_REASON_QUIT,
+};
+use log::{error, info};
+use neodlp_protocol::DownloadEvent;
+use sqlx::{Pool, Row, Sqlite};
+use std::{sync::Arc, time::Duration};
+use tauri::{AppHandle, Manager, State};
+use tokio::{
+    sync::{Mutex, Notify},
+    time::timeout,
+};
+
+// Fallback polling interval, the scheduler is normally woken up through `notify`
+const SCHEDULER_TICK: Duration = Duration::from_secs(5);
+
+/// Starts `queued` downloads in `queue_index` order while honoring the
+/// `max_parallel_downloads` setting.
+#[derive(Default)]
+pub struct QueueScheduler {
+    wakeup: Notify,
+}
+
+impl QueueScheduler {
+    /// Asks the scheduler to re-check the queue as soon as possible.
+    pub fn notify(&self) {
+        self.wakeup.notify_one();
+    }
+}
+
+/// Wakes up the scheduler if it is managed by the app.
+pub fn notify_scheduler(app_handle: &AppHandle) {
+    if let Some(scheduler) = app_handle.try_state::<QueueScheduler>() {
+        scheduler.notify();
+    }
+}
+
+async fn get_download_config(app_handle: &AppHandle) -> DownloadConfig {
+    let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+    let state = state.lock().await;
+    state.config.download.clone()
+}
+
+// A fresh download is started with `--no-continue`, its resumption must keep the `.part` files
+fn continue_download_args(download_args: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(download_args) {
+        Ok(args) => {
+            let args: Vec<String> = args
+                .into_iter()
+                .map(|arg| match arg.as_str() {
+                    "--no-continue" => "--continue".to_string(),
+                    _ => arg,
+                })
+                .collect();
+            serde_json::to_string(&args).unwrap_or_else(|_| download_args.to_string())
+        }
+        // Left as is, the scheduler reports the invalid args when starting it
+        Err(_) => download_args.to_string(),
+    }
+}
+
+/// Re-queues downloads that were paused because the app quit (not by the user),
+/// keeping their previous order. Does nothing if `auto_resume_downloads` is off.
+pub async fn resume_downloads_paused_on_quit(
+    app_handle: &AppHandle,
+    pool: &Pool<Sqlite>,
+) -> Result<u64, String> {
+    if !get_download_config(app_handle).await.auto_resume_downloads {
+        info!("Auto resume of paused downloads is disabled");
+        return Ok(0);
+    }
+
+    let paused = sqlx::query(
+        "SELECT id, download_args FROM downloads
+        WHERE download_status = 'paused' AND pause_reason = ?
+        ORDER BY queue_index ASC, id ASC",
+    )
+    .bind(PAUSE_REASON_QUIT)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut resumed = 0;
+    for row in paused {
+        let id: i64 = row.get("id");
+        let download_args: Option<String> = row.get("download_args");
+        resumed += sqlx::query(
+            "UPDATE downloads SET
+                download_status = 'queued',
+                download_args = ?,
+                queue_index = (SELECT COALESCE(MAX(queue_index) + 1, 0) FROM downloads WHERE download_status = 'queued')
+            WHERE id = ?",
+        )
+        .bind(download_args.map(|args| continue_download_args(&args)))
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    }
+
+    if resumed > 0 {
+        info!("Re-queued {} downloads paused on quit", resumed);
+    }
+    Ok(resumed)
+}
+
+async fn process_queue(app_handle: &AppHandle) -> Result<(), String> {
+    // Never start new downloads while the app is pausing everything to exit
+    if is_shutting_down(app_handle) {
+        return Ok(());
+    }
+
+    // The database connection is established asynchronously during setup
+    let Ok(pool) = get_pool(app_handle) else {
+        return Ok(());
+    };
+    let max_parallel = i64::from(get_download_config(app_handle).await.max_parallel_downloads);
+
+    // Suspended downloads still hold their process, so they keep occupying a slot
+    let running: i64 = sqlx::query(
+        "SELECT COUNT(*) AS running FROM downloads WHERE download_status IN ('downloading', 'starting', 'suspended')",
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .get("running");
+
+    let free_slots = max_parallel - running;
+    if free_slots <= 0 {
+        return Ok(());
+    }
+
+    let queued = sqlx::query(
+        "SELECT download_id, download_args FROM downloads
+        WHERE download_status = 'queued' AND download_args IS NOT NULL
+        ORDER BY queue_index ASC, id ASC
+        LIMIT ?",
+    )
+    .bind(free_slots)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for row in queued {
+        let download_id: String = row.get("download_id");
+        let download_args: String = row.get("download_args");
+
+        // Only promote the row if nobody else (e.g. the user) changed it meanwhile
+        let promoted = sqlx::query(
+            "UPDATE downloads SET download_status = 'starting' WHERE download_id = ? AND download_status = 'queued'",
+        )
+        .bind(&download_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        if promoted == 0 {
+            continue;
+        }
+
+        info!("Starting queued download: {}", download_id);
+        let result = serde_json::from_str::<Vec<String>>(&download_args)
+            .map_err(|e| format!("Invalid download args: {}", e));
+        let result = match result {
+            Ok(args) => spawn_download(app_handle, &download_id, args).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            error!("Failed to start queued download {}: {}", download_id, e);
+            let _ = sqlx::query(
+                "UPDATE downloads SET download_status = 'errored' WHERE download_id = ?",
+            )
+            .bind(&download_id)
+            .execute(&pool)
+            .await;
+            extension::publish(
+                app_handle,
+                DownloadEvent::Failed {
+                    download_id,
+                    message: e,
+                },
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the queue scheduler for the whole lifetime of the app, independent of
+/// the webview, so queued downloads keep progressing while hidden in the tray.
+pub async fn run_scheduler(app_handle: AppHandle) {
+    info!("Starting download queue scheduler");
+    loop {
+        if let Err(e) = process_queue(&app_handle).await {
+            error!("Error processing download queue: {}", e);
+        }
+
+        let scheduler = app_handle.state::<QueueScheduler>();
+        let _ = timeout(SCHEDULER_TICK, scheduler.wakeup.notified()).await;
+    }
+}
+
+#[tauri::command]
+pub async fn enqueue_download(
+    app_handle: AppHandle,
+    scheduler: State<'_, QueueScheduler>,
+    download_id: String,
+    args: Vec<String>,
+) -> Result<i64, String> {
+    let pool = get_pool(&app_handle)?;
+    let download_args = serde_json::to_string(&args).map_err(|e| e.to_string())?;
+
+    let queue_index: i64 = sqlx::query(
+        "UPDATE downloads SET
+            download_status = 'queued',
+            download_args = ?,
+            queue_index = (SELECT COALESCE(MAX(queue_index) + 1, 0) FROM downloads WHERE download_status = 'queued')
+        WHERE download_id = ?
+        RETURNING queue_index",
+    )
+    .bind(download_args)
+    .bind(&download_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Download {} does not exist", download_id))?
+    .get("queue_index");
+
+    info!(
+        "Download queued with id: {} at index {}",
+        download_id, queue_index
+    );
+    extension::publish_queued(&app_handle, &download_id).await;
+    scheduler.notify();
+    Ok(queue_index)
+}
+
+#[tauri::command]
+pub async fn process_download_queue(scheduler: State<'_, QueueScheduler>) -> Result<(), String> {
+    scheduler.notify();
+    Ok(())
+}
@@ -0,0 +1,889 @@
+use crate::{
+    database::Database,
+    models::{
+        Download, DownloadDetails, DownloadFilter, DownloadStatus, KeyValue, Page, PlaylistInfo,
+        VideoInfo,
+    },
+};
+use sqlx::{AssertSqlSafe, Pool, QueryBuilder, Row, Sqlite};
+use tauri::State;
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 500;
+
+const DOWNLOAD_DETAILS_SELECT: &str = "SELECT
+        downloads.*,
+        video_info.title,
+        video_info.url,
+        video_info.host,
+        video_info.thumbnail,
+        video_info.channel,
+        video_info.duration_string,
+        video_info.release_date,
+        video_info.view_count,
+        video_info.like_count,
+        playlist_info.playlist_title,
+        playlist_info.playlist_url,
+        playlist_info.playlist_n_entries,
+        playlist_info.playlist_channel
+    FROM downloads
+    INNER JOIN video_info
+        ON downloads.video_id = video_info.video_id
+    LEFT JOIN playlist_info
+        ON downloads.playlist_id = playlist_info.playlist_id
+        AND downloads.playlist_id IS NOT NULL";
+
+// Downloads
+
+pub async fn get_download(
+    pool: &Pool<Sqlite>,
+    download_id: &str,
+) -> Result<Option<DownloadDetails>, sqlx::Error> {
+    sqlx::query_as::<_, DownloadDetails>(AssertSqlSafe(format!(
+        "{} WHERE downloads.download_id = ?",
+        DOWNLOAD_DETAILS_SELECT
+    )))
+    .bind(download_id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn push_download_filter(builder: &mut QueryBuilder<Sqlite>, filter: &DownloadFilter) {
+    builder.push(" WHERE 1 = 1");
+    if !filter.statuses.is_empty() {
+        builder.push(" AND downloads.download_status IN (");
+        let mut statuses = builder.separated(", ");
+        for status in &filter.statuses {
+            statuses.push_bind(status.as_str());
+        }
+        statuses.push_unseparated(")");
+    }
+    if let Some(video_id) = &filter.video_id {
+        builder
+            .push(" AND downloads.video_id = ")
+            .push_bind(video_id.clone());
+    }
+    if let Some(playlist_id) = &filter.playlist_id {
+        builder
+            .push(" AND downloads.playlist_id = ")
+            .push_bind(playlist_id.clone());
+    }
+    if let Some(search) = filter
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let pattern = format!("%{}%", search);
+        builder
+            .push(" AND (video_info.title LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR video_info.channel LIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+}
+
+pub async fn list_downloads(
+    pool: &Pool<Sqlite>,
+    filter: &DownloadFilter,
+) -> Result<Page<DownloadDetails>, sqlx::Error> {
+    let page = filter.page.unwrap_or(1).max(1);
+    let per_page = filter
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let mut count_builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*) AS total FROM downloads
+        INNER JOIN video_info ON downloads.video_id = video_info.video_id",
+    );
+    push_download_filter(&mut count_builder, filter);
+    let total: i64 = count_builder.build().fetch_one(pool).await?.get("total");
+
+    let mut builder = QueryBuilder::<Sqlite>::new(DOWNLOAD_DETAILS_SELECT);
+    push_download_filter(&mut builder, filter);
+    builder
+        .push(" ORDER BY downloads.id DESC LIMIT ")
+        .push_bind(i64::from(per_page))
+        .push(" OFFSET ")
+        // Computed in i64, `page` comes from the frontend and may be any u32
+        .push_bind((i64::from(page) - 1) * i64::from(per_page));
+    let items = builder
+        .build_query_as::<DownloadDetails>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Page {
+        items,
+        total,
+        page,
+        per_page,
+    })
+}
+
+pub async fn save_download(pool: &Pool<Sqlite>, download: &Download) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO downloads (
+            download_id, download_status, video_id, format_id, subtitle_id, queue_index,
+            playlist_id, playlist_indices, resolution, ext, abr, vbr, acodec, vcodec,
+            dynamic_range, process_id, status, item, progress, total, downloaded, speed, eta,
+            filepath, filetype, filesize, output_format, embed_metadata, embed_thumbnail,
+            square_crop_thumbnail, sponsorblock_remove, sponsorblock_mark, use_aria2,
+            custom_command, queue_config, download_args, pause_reason
+        ) VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+            $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37
+        )
+        ON CONFLICT(download_id) DO UPDATE SET
+            download_status = $2, video_id = $3, format_id = $4, subtitle_id = $5,
+            queue_index = $6, playlist_id = $7, playlist_indices = $8, resolution = $9,
+            ext = $10, abr = $11, vbr = $12, acodec = $13, vcodec = $14, dynamic_range = $15,
+            process_id = $16, status = $17, item = $18, progress = $19, total = $20,
+            downloaded = $21, speed = $22, eta = $23, filepath = $24, filetype = $25,
+            filesize = $26, output_format = $27, embed_metadata = $28, embed_thumbnail = $29,
+            square_crop_thumbnail = $30, sponsorblock_remove = $31, sponsorblock_mark = $32,
+            use_aria2 = $33, custom_command = $34, queue_config = $35, download_args = $36,
+            pause_reason = $37",
+    )
+    .bind(&download.download_id)
+    .bind(download.download_status.as_str())
+    .bind(&download.video_id)
+    .bind(&download.format_id)
+    .bind(&download.subtitle_id)
+    .bind(download.queue_index)
+    .bind(&download.playlist_id)
+    .bind(&download.playlist_indices)
+    .bind(&download.resolution)
+    .bind(&download.ext)
+    .bind(download.abr)
+    .bind(download.vbr)
+    .bind(&download.acodec)
+    .bind(&download.vcodec)
+    .bind(&download.dynamic_range)
+    .bind(download.process_id)
+    .bind(&download.status)
+    .bind(&download.item)
+    .bind(download.progress)
+    .bind(download.total)
+    .bind(download.downloaded)
+    .bind(download.speed)
+    .bind(download.eta)
+    .bind(&download.filepath)
+    .bind(&download.filetype)
+    .bind(download.filesize)
+    .bind(&download.output_format)
+    .bind(download.embed_metadata)
+    .bind(download.embed_thumbnail)
+    .bind(download.square_crop_thumbnail)
+    .bind(&download.sponsorblock_remove)
+    .bind(&download.sponsorblock_mark)
+    .bind(download.use_aria2)
+    .bind(&download.custom_command)
+    .bind(&download.queue_config)
+    .bind(&download.download_args)
+    .bind(&download.pause_reason)
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn update_download_status(
+    pool: &Pool<Sqlite>,
+    download_id: &str,
+    download_status: DownloadStatus,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query("UPDATE downloads SET download_status = ? WHERE download_id = ?")
+        .bind(download_status.as_str())
+        .bind(download_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+pub async fn delete_download(pool: &Pool<Sqlite>, download_id: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM downloads WHERE download_id = ?")
+        .bind(download_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+// Video info
+
+pub async fn get_video_info(
+    pool: &Pool<Sqlite>,
+    video_id: &str,
+) -> Result<Option<VideoInfo>, sqlx::Error> {
+    sqlx::query_as::<_, VideoInfo>("SELECT * FROM video_info WHERE video_id = ?")
+        .bind(video_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn save_video_info(
+    pool: &Pool<Sqlite>,
+    video_info: &VideoInfo,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO video_info (
+            video_id, title, url, host, thumbnail, channel, duration_string, release_date,
+            view_count, like_count
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT(video_id) DO UPDATE SET
+            title = $2, url = $3, host = $4, thumbnail = $5, channel = $6,
+            duration_string = $7, release_date = $8, view_count = $9, like_count = $10",
+    )
+    .bind(&video_info.video_id)
+    .bind(&video_info.title)
+    .bind(&video_info.url)
+    .bind(&video_info.host)
+    .bind(&video_info.thumbnail)
+    .bind(&video_info.channel)
+    .bind(&video_info.duration_string)
+    .bind(&video_info.release_date)
+    .bind(video_info.view_count)
+    .bind(video_info.like_count)
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn delete_video_info(pool: &Pool<Sqlite>, video_id: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM video_info WHERE video_id = ?")
+        .bind(video_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+// Playlist info
+
+pub async fn get_playlist_info(
+    pool: &Pool<Sqlite>,
+    playlist_id: &str,
+) -> Result<Option<PlaylistInfo>, sqlx::Error> {
+    sqlx::query_as::<_, PlaylistInfo>("SELECT * FROM playlist_info WHERE playlist_id = ?")
+        .bind(playlist_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn save_playlist_info(
+    pool: &Pool<Sqlite>,
+    playlist_info: &PlaylistInfo,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO playlist_info (
+            playlist_id, playlist_title, playlist_url, playlist_n_entries, playlist_channel
+        ) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT(playlist_id) DO UPDATE SET
+            playlist_title = $2, playlist_url = $3, playlist_n_entries = $4, playlist_channel = $5",
+    )
+    .bind(&playlist_info.playlist_id)
+    .bind(&playlist_info.playlist_title)
+    .bind(&playlist_info.playlist_url)
+    .bind(playlist_info.playlist_n_entries)
+    .bind(&playlist_info.playlist_channel)
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn delete_playlist_info(
+    pool: &Pool<Sqlite>,
+    playlist_id: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM playlist_info WHERE playlist_id = ?")
+        .bind(playlist_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+// Settings and kv store, both tables share the same `{"value": ...}` layout.
+// The table name is never user input, it is always one of the two constants below.
+
+const SETTINGS_TABLE: &str = "settings";
+const KV_STORE_TABLE: &str = "kv_store";
+
+async fn list_key_values(pool: &Pool<Sqlite>, table: &str) -> Result<Vec<KeyValue>, sqlx::Error> {
+    let rows = sqlx::query(AssertSqlSafe(format!(
+        "SELECT key, value FROM {} ORDER BY key",
+        table
+    )))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let value: String = row.get("value");
+            KeyValue {
+                key: row.get("key"),
+                value: serde_json::from_str::<serde_json::Value>(&value)
+                    .ok()
+                    .and_then(|mut json| json.get_mut("value").map(serde_json::Value::take))
+                    .unwrap_or(serde_json::Value::Null),
+            }
+        })
+        .collect())
+}
+
+async fn save_key_value(
+    pool: &Pool<Sqlite>,
+    table: &str,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(AssertSqlSafe(format!(
+        "INSERT INTO {} (key, value) VALUES ($1, json_object('value', json($2)))
+        ON CONFLICT(key) DO UPDATE SET value = json_object('value', json($2))",
+        table
+    )))
+    .bind(key)
+    .bind(value.to_string())
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+async fn delete_key_value(
+    pool: &Pool<Sqlite>,
+    table: &str,
+    key: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query(AssertSqlSafe(format!(
+        "DELETE FROM {} WHERE key = ?",
+        table
+    )))
+    .bind(key)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+pub async fn list_settings(pool: &Pool<Sqlite>) -> Result<Vec<KeyValue>, sqlx::Error> {
+    list_key_values(pool, SETTINGS_TABLE).await
+}
+
+pub async fn save_setting(
+    pool: &Pool<Sqlite>,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    save_key_value(pool, SETTINGS_TABLE, key, value).await
+}
+
+pub async fn reset_settings(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM settings")
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn list_kv_pairs(pool: &Pool<Sqlite>) -> Result<Vec<KeyValue>, sqlx::Error> {
+    list_key_values(pool, KV_STORE_TABLE).await
+}
+
+pub async fn save_kv_pair(
+    pool: &Pool<Sqlite>,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    save_key_value(pool, KV_STORE_TABLE, key, value).await
+}
+
+pub async fn delete_kv_pair(pool: &Pool<Sqlite>, key: &str) -> Result<bool, sqlx::Error> {
+    delete_key_value(pool, KV_STORE_TABLE, key).await
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn db_get_download(
+    database: State<'_, Database>,
+    download_id: String,
+) -> Result<Option<DownloadDetails>, String> {
+    let pool = database.pool()?;
+    get_download(&pool, &download_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_list_downloads(
+    database: State<'_, Database>,
+    filter: Option<DownloadFilter>,
+) -> Result<Page<DownloadDetails>, String> {
+    let pool = database.pool()?;
+    list_downloads(&pool, &filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_save_download(
+    database: State<'_, Database>,
+    download: Download,
+) -> Result<(), String> {
+    let pool = database.pool()?;
+    save_download(&pool, &download)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_update_download_status(
+    database: State<'_, Database>,
+    download_id: String,
+    download_status: DownloadStatus,
+) -> Result<bool, String> {
+    let pool = database.pool()?;
+    update_download_status(&pool, &download_id, download_status)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_delete_download(
+    database: State<'_, Database>,
+    download_id: String,
+) -> Result<bool, String> {
+    let pool = database.pool()?;
+    delete_download(&pool, &download_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_get_video_info(
+    database: State<'_, Database>,
+    video_id: String,
+) -> Result<Option<VideoInfo>, String> {
+    let pool = database.pool()?;
+    get_video_info(&pool, &video_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_save_video_info(
+    database: State<'_, Database>,
+    video_info: VideoInfo,
+) -> Result<(), String> {
+    let pool = database.pool()?;
+    save_video_info(&pool, &video_info)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_delete_video_info(
+    database: State<'_, Database>,
+    video_id: String,
+) -> Result<bool, String> {
+    let pool = database.pool()?;
+    delete_video_info(&pool, &video_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_get_playlist_info(
+    database: State<'_, Database>,
+    playlist_id: String,
+) -> Result<Option<PlaylistInfo>, String> {
+    let pool = database.pool()?;
+    get_playlist_info(&pool, &playlist_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_save_playlist_info(
+    database: State<'_, Database>,
+    playlist_info: PlaylistInfo,
+) -> Result<(), String> {
+    let pool = database.pool()?;
+    save_playlist_info(&pool, &playlist_info)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_delete_playlist_info(
+    database: State<'_, Database>,
+    playlist_id: String,
+) -> Result<bool, String> {
+    let pool = database.pool()?;
+    delete_playlist_info(&pool, &playlist_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_list_settings(database: State<'_, Database>) -> Result<Vec<KeyValue>, String> {
+    let pool = database.pool()?;
+    list_settings(&pool).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_save_setting(
+    database: State<'_, Database>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let pool = database.pool()?;
+    save_setting(&pool, &key, &value)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_reset_settings(database: State<'_, Database>) -> Result<(), String> {
+    let pool = database.pool()?;
+    reset_settings(&pool).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_list_kv_pairs(database: State<'_, Database>) -> Result<Vec<KeyValue>, String> {
+    let pool = database.pool()?;
+    list_kv_pairs(&pool).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_save_kv_pair(
+    database: State<'_, Database>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let pool = database.pool()?;
+    save_kv_pair(&pool, &key, &value)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn db_delete_kv_pair(database: State<'_, Database>, key: String) -> Result<bool, String> {
+    let pool = database.pool()?;
+    delete_kv_pair(&pool, &key).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::get_migrator;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const ALL_STATUSES: [DownloadStatus; 8] = [
+        DownloadStatus::Queued,
+        DownloadStatus::Starting,
+        DownloadStatus::Downloading,
+        DownloadStatus::Paused,
+        DownloadStatus::Suspended,
+        DownloadStatus::Interrupted,
+        DownloadStatus::Completed,
+        DownloadStatus::Errored,
+    ];
+
+    // A single connection, every connection to `sqlite::memory:` gets its own database
+    async fn test_pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        get_migrator().run(&pool).await.unwrap();
+        pool
+    }
+
+    fn video_info(video_id: &str, title: &str, channel: &str) -> VideoInfo {
+        VideoInfo {
+            id: None,
+            video_id: video_id.to_string(),
+            title: title.to_string(),
+            url: format!("https://example.com/{}", video_id),
+            host: "example.com".to_string(),
+            thumbnail: None,
+            channel: Some(channel.to_string()),
+            duration_string: None,
+            release_date: None,
+            view_count: None,
+            like_count: None,
+        }
+    }
+
+    fn download(download_id: &str, video_id: &str, download_status: DownloadStatus) -> Download {
+        Download {
+            id: None,
+            download_id: download_id.to_string(),
+            download_status,
+            video_id: video_id.to_string(),
+            format_id: "best".to_string(),
+            subtitle_id: None,
+            queue_index: None,
+            playlist_id: None,
+            playlist_indices: None,
+            resolution: None,
+            ext: None,
+            abr: None,
+            vbr: None,
+            acodec: None,
+            vcodec: None,
+            dynamic_range: None,
+            process_id: None,
+            status: None,
+            item: None,
+            progress: None,
+            total: None,
+            downloaded: None,
+            speed: None,
+            eta: None,
+            filepath: None,
+            filetype: None,
+            filesize: None,
+            output_format: None,
+            embed_metadata: false,
+            embed_thumbnail: true,
+            square_crop_thumbnail: false,
+            sponsorblock_remove: None,
+            sponsorblock_mark: None,
+            use_aria2: false,
+            custom_command: None,
+            queue_config: None,
+            download_args: None,
+            pause_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    // Downloads "d1" to "d4" (listed newest first), "d4" belongs to a playlist
+    async fn seeded_pool() -> Pool<Sqlite> {
+        let pool = test_pool().await;
+        save_video_info(&pool, &video_info("v1", "First Video", "Alpha"))
+            .await
+            .unwrap();
+        save_video_info(&pool, &video_info("v2", "Second Video", "Beta"))
+            .await
+            .unwrap();
+        let playlist_info = PlaylistInfo {
+            id: None,
+            playlist_id: "p1".to_string(),
+            playlist_title: "Playlist".to_string(),
+            playlist_url: "https://example.com/p1".to_string(),
+            playlist_n_entries: 1,
+            playlist_channel: None,
+        };
+        save_playlist_info(&pool, &playlist_info).await.unwrap();
+
+        let mut playlist_download = download("d4", "v2", DownloadStatus::Queued);
+        playlist_download.playlist_id = Some("p1".to_string());
+        for download in [
+            download("d1", "v1", DownloadStatus::Completed),
+            download("d2", "v1", DownloadStatus::Paused),
+            download("d3", "v2", DownloadStatus::Errored),
+            playlist_download,
+        ] {
+            save_download(&pool, &download).await.unwrap();
+        }
+        pool
+    }
+
+    fn download_ids(page: &Page<DownloadDetails>) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|details| details.download.download_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn download_status_round_trips() {
+        for status in ALL_STATUSES {
+            assert_eq!(DownloadStatus::try_from(status.to_string()), Ok(status));
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, format!("\"{}\"", status.as_str()));
+            assert_eq!(
+                serde_json::from_str::<DownloadStatus>(&json).unwrap(),
+                status
+            );
+        }
+        assert!(DownloadStatus::try_from("unknown".to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn download_status_round_trips_through_the_database() {
+        let pool = test_pool().await;
+        save_video_info(&pool, &video_info("v1", "Video", "Channel"))
+            .await
+            .unwrap();
+        save_download(&pool, &download("d1", "v1", DownloadStatus::Queued))
+            .await
+            .unwrap();
+
+        for status in ALL_STATUSES {
+            assert!(update_download_status(&pool, "d1", status).await.unwrap());
+            let details = get_download(&pool, "d1").await.unwrap().unwrap();
+            assert_eq!(details.download.download_status, status);
+        }
+        assert!(
+            !update_download_status(&pool, "missing", DownloadStatus::Paused)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn saved_download_is_listed_with_its_video_info() {
+        let pool = seeded_pool().await;
+        let details = get_download(&pool, "d4").await.unwrap().unwrap();
+        assert_eq!(details.title, "Second Video");
+        assert_eq!(details.channel.as_deref(), Some("Beta"));
+        assert_eq!(details.download.playlist_id.as_deref(), Some("p1"));
+        assert!(details.download.embed_thumbnail);
+        assert!(!details.download.embed_metadata);
+        assert!(get_download(&pool, "missing").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn empty_filter_only_adds_the_where_clause() {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM downloads");
+        push_download_filter(&mut builder, &DownloadFilter::default());
+        assert_eq!(builder.sql(), "SELECT * FROM downloads WHERE 1 = 1");
+    }
+
+    #[test]
+    fn filter_binds_every_value() {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM downloads");
+        let filter = DownloadFilter {
+            statuses: vec![DownloadStatus::Queued, DownloadStatus::Paused],
+            video_id: Some("v1".to_string()),
+            playlist_id: Some("p1".to_string()),
+            search: Some(" video ".to_string()),
+            ..Default::default()
+        };
+        push_download_filter(&mut builder, &filter);
+        assert_eq!(
+            builder.sql(),
+            "SELECT * FROM downloads WHERE 1 = 1 \
+            AND downloads.download_status IN (?, ?) \
+            AND downloads.video_id = ? \
+            AND downloads.playlist_id = ? \
+            AND (video_info.title LIKE ? OR video_info.channel LIKE ?)"
+        );
+    }
+
+    #[test]
+    fn blank_search_is_ignored() {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM downloads");
+        let filter = DownloadFilter {
+            search: Some("   ".to_string()),
+            ..Default::default()
+        };
+        push_download_filter(&mut builder, &filter);
+        assert_eq!(builder.sql(), "SELECT * FROM downloads WHERE 1 = 1");
+    }
+
+    #[tokio::test]
+    async fn list_downloads_applies_the_filter() {
+        let pool = seeded_pool().await;
+
+        let all = list_downloads(&pool, &DownloadFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(download_ids(&all), ["d4", "d3", "d2", "d1"]);
+        assert_eq!(all.total, 4);
+
+        let filter = DownloadFilter {
+            statuses: vec![DownloadStatus::Completed, DownloadStatus::Paused],
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!(download_ids(&page), ["d2", "d1"]);
+        assert_eq!(page.total, 2);
+
+        let filter = DownloadFilter {
+            video_id: Some("v2".to_string()),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!(download_ids(&page), ["d4", "d3"]);
+
+        let filter = DownloadFilter {
+            playlist_id: Some("p1".to_string()),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!(download_ids(&page), ["d4"]);
+
+        // Matches the title and the channel, case-insensitively
+        for search in ["first", "ALPHA"] {
+            let filter = DownloadFilter {
+                search: Some(search.to_string()),
+                ..Default::default()
+            };
+            let page = list_downloads(&pool, &filter).await.unwrap();
+            assert_eq!(download_ids(&page), ["d2", "d1"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_downloads_paginates() {
+        let pool = seeded_pool().await;
+
+        let filter = DownloadFilter {
+            page: Some(2),
+            per_page: Some(3),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!(download_ids(&page), ["d1"]);
+        assert_eq!((page.page, page.per_page, page.total), (2, 3, 4));
+
+        let filter = DownloadFilter {
+            page: Some(3),
+            per_page: Some(3),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.total, 4);
+
+        let filter = DownloadFilter {
+            page: Some(u32::MAX),
+            per_page: Some(MAX_PER_PAGE),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.page, u32::MAX);
+    }
+
+    #[tokio::test]
+    async fn list_downloads_clamps_the_page_and_page_size() {
+        let pool = seeded_pool().await;
+
+        let page = list_downloads(&pool, &DownloadFilter::default())
+            .await
+            .unwrap();
+        assert_eq!((page.page, page.per_page), (1, DEFAULT_PER_PAGE));
+
+        let filter = DownloadFilter {
+            page: Some(0),
+            per_page: Some(0),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!((page.page, page.per_page), (1, 1));
+        assert_eq!(download_ids(&page), ["d4"]);
+
+        let filter = DownloadFilter {
+            per_page: Some(MAX_PER_PAGE + 1),
+            ..Default::default()
+        };
+        let page = list_downloads(&pool, &filter).await.unwrap();
+        assert_eq!(page.per_page, MAX_PER_PAGE);
+        assert_eq!(page.items.len(), 4);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    config::{apply_patch, setting_patch, Config},
+    repository::list_settings,
+};
+use log::warn;
+use sqlx::{Pool, Sqlite};
+
+/// Returns `config` with the settings kept in the config copied over from the
+/// settings table, values the config rejects are skipped.
+pub async fn import_config_settings(
+    pool: &Pool<Sqlite>,
+    config: &Config,
+) -> Result<Config, String> {
+    let settings = list_settings(pool).await.map_err(|e| e.to_string())?;
+    let mut config = config.clone();
+    for setting in settings {
+        // The port has always been read from the config file
+        if setting.key == "websocket_port" {
+            continue;
+        }
+        let Some(patch) = setting_patch(&setting.key, &setting.value) else {
+            continue;
+        };
+        match apply_patch(&config, &patch) {
+            Ok(patched) => config = patched,
+            Err(e) => warn!(
+                "Not importing setting {} into the config: {}",
+                setting.key, e
+            ),
+        }
+    }
+    Ok(config)
+}
@@ -0,0 +1,226 @@
+use crate::{
+    database::get_pool, downloader::DownloadManager, fetch_ongoing_downloads, kill_all_process,
+    process::wait_for_exit, DownloadState, WebSocketState, PAUSE_REASON_QUIT,
+};
+use futures_util::future::join_all;
+use log::{error, info, warn};
+use sqlx::{Pool, Sqlite};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+// Time given to a process after each escalated signal (SIGTERM, SIGKILL)
+#[cfg(unix)]
+const ESCALATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Makes sure every yt-dlp process is stopped and recorded as paused before the app exits.
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    started: AtomicBool,
+    finished: AtomicBool,
+}
+
+pub fn is_shutting_down(app_handle: &AppHandle) -> bool {
+    app_handle
+        .try_state::<ShutdownCoordinator>()
+        .is_some_and(|coordinator| coordinator.started.load(Ordering::SeqCst))
+}
+
+pub fn is_shutdown_finished(app_handle: &AppHandle) -> bool {
+    app_handle
+        .try_state::<ShutdownCoordinator>()
+        .is_none_or(|coordinator| coordinator.finished.load(Ordering::SeqCst))
+}
+
+// How a process reacted to `stop_process`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stopped {
+    Interrupted,
+    #[cfg(unix)]
+    Terminated,
+    #[cfg(unix)]
+    Killed,
+    StillRunning,
+}
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stopped::Interrupted => "interrupted",
+            #[cfg(unix)]
+            Stopped::Terminated => "terminated",
+            #[cfg(unix)]
+            Stopped::Killed => "killed",
+            Stopped::StillRunning => "still running",
+        })
+    }
+}
+
+// Interrupts the process, escalating to SIGTERM and SIGKILL if it doesn't exit in time
+async fn stop_process(pid: i32, timeout: Duration) -> Stopped {
+    if let Err(e) = kill_all_process(pid).await {
+        warn!("Failed to interrupt process {}: {}", pid, e);
+    }
+    if wait_for_exit(pid, timeout).await {
+        return Stopped::Interrupted;
+    }
+
+    #[cfg(unix)]
+    {
+        use crate::process::signal_process_tree;
+
+        warn!(
+            "Process {} did not exit after {:?}, sending SIGTERM",
+            pid, timeout
+        );
+        let _ = signal_process_tree(pid, libc::SIGTERM);
+        if wait_for_exit(pid, ESCALATION_TIMEOUT).await {
+            return Stopped::Terminated;
+        }
+
+        warn!(
+            "Process {} did not exit after SIGTERM, sending SIGKILL",
+            pid
+        );
+        let _ = signal_process_tree(pid, libc::SIGKILL);
+        if wait_for_exit(pid, ESCALATION_TIMEOUT).await {
+            return Stopped::Killed;
+        }
+    }
+
+    Stopped::StillRunning
+}
+
+async fn pause_download(
+    app_handle: &AppHandle,
+    pool: &Pool<Sqlite>,
+    download: DownloadState,
+    timeout: Duration,
+) {
+    let manager = app_handle.state::<DownloadManager>();
+    let managed_pid = manager.request_stop(&download.download_id).await;
+
+    if let Some(pid) = managed_pid.map(|pid| pid as i32).or(download.process_id) {
+        let outcome = stop_process(pid, timeout).await;
+        info!(
+            "Download {} process {} stopped: {}",
+            download.download_id, pid, outcome
+        );
+
+        // Keep the PID, so the next startup can tell whether the process is gone
+        if outcome == Stopped::StillRunning {
+            if let Err(e) = sqlx::query(
+                "UPDATE downloads SET download_status = 'interrupted', process_id = ? WHERE id = ?",
+            )
+            .bind(pid)
+            .bind(download.id)
+            .execute(pool)
+            .await
+            {
+                error!(
+                    "Failed to update download status for ID {}: {}",
+                    download.id, e
+                );
+            }
+            return;
+        }
+    }
+
+    if let Err(e) = sqlx::query(
+        "UPDATE downloads SET download_status = 'paused', pause_reason = ?, process_id = NULL WHERE id = ?",
+    )
+    .bind(PAUSE_REASON_QUIT)
+    .bind(download.id)
+    .execute(pool)
+    .await
+    {
+        error!(
+            "Failed to update download status for ID {}: {}",
+            download.id, e
+        );
+    }
+}
+
+/// Pauses all ongoing downloads, waits for their processes to exit and then exits the app.
+/// Safe to call multiple times, only the first call does the work.
+pub async fn shutdown(app_handle: AppHandle, exit_code: i32) {
+    let coordinator = app_handle.state::<ShutdownCoordinator>();
+    if coordinator.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    info!("Shutting down, pausing ongoing downloads...");
+
+    if let Ok(pool) = get_pool(&app_handle) {
+        // Time yt-dlp gets to flush `.part` files after SIGINT
+        let timeout = {
+            let state = app_handle.state::<Arc<Mutex<WebSocketState>>>();
+            let state = state.lock().await;
+            Duration::from_secs(u64::from(state.config.download.shutdown_timeout))
+        };
+
+        match fetch_ongoing_downloads(&pool).await {
+            Ok(downloads) => {
+                info!("Found {} ongoing downloads to pause", downloads.len());
+                join_all(
+                    downloads
+                        .into_iter()
+                        .map(|download| pause_download(&app_handle, &pool, download, timeout)),
+                )
+                .await;
+            }
+            Err(e) => error!("Failed to fetch ongoing downloads: {}", e),
+        }
+    }
+
+    coordinator.finished.store(true, Ordering::SeqCst);
+    info!("Shutdown complete, exiting with code {}", exit_code);
+    app_handle.exit(exit_code);
+}
+
+#[cfg(unix)]
+async fn wait_for_termination_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    Ok(tokio::select! {
+        _ = sigterm.recv() => "SIGTERM",
+        _ = sigint.recv() => "SIGINT",
+        _ = sighup.recv() => "SIGHUP",
+    })
+}
+
+#[cfg(windows)]
+async fn wait_for_termination_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::windows::{ctrl_c, ctrl_close, ctrl_logoff, ctrl_shutdown};
+
+    let mut ctrl_c = ctrl_c()?;
+    let mut ctrl_close = ctrl_close()?;
+    let mut ctrl_logoff = ctrl_logoff()?;
+    let mut ctrl_shutdown = ctrl_shutdown()?;
+    Ok(tokio::select! {
+        _ = ctrl_c.recv() => "CTRL_C",
+        _ = ctrl_close.recv() => "CTRL_CLOSE",
+        _ = ctrl_logoff.recv() => "CTRL_LOGOFF",
+        _ = ctrl_shutdown.recv() => "CTRL_SHUTDOWN",
+    })
+}
+
+/// Runs a graceful shutdown when the OS asks the app to terminate (logout, system shutdown, kill).
+pub async fn handle_termination_signals(app_handle: AppHandle) {
+    match wait_for_termination_signal().await {
+        Ok(signal) => {
+            info!("Received {}, shutting down gracefully", signal);
+            shutdown(app_handle, 0).await;
+        }
+        Err(e) => error!("Failed to listen for termination signals: {}", e),
+    }
+}
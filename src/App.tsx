@@ -4,7 +4,7 @@ import { AppContext } from "@/providers/appContextProvider";
 import { useEffect, useRef, useState } from "react";
 import { arch, exeExtension } from "@tauri-apps/plugin-os";
 import { downloadDir, join, resourceDir, tempDir, dataDir } from "@tauri-apps/api/path";
-import { useBasePathsStore, useCurrentVideoMetadataStore, useDownloadActionStatesStore, useDownloaderPageStatesStore, useDownloadStatesStore, useEnvironmentStore, useKvPairsStatesStore, useSettingsPageStatesStore } from "@/services/store";
+import { useBasePathsStore, useCurrentVideoMetadataStore, useDownloaderPageStatesStore, useDownloadStatesStore, useEnvironmentStore, useKvPairsStatesStore, useSettingsPageStatesStore } from "@/services/store";
 import { isObjEmpty} from "@/utils";
 import { Command } from "@tauri-apps/plugin-shell";
 import { useUpdateDownloadStatus } from "@/services/mutations";
@@ -16,7 +16,8 @@ import { useYtDlpUpdater } from "@/helpers/use-ytdlp-updater";
 import { getVersion } from "@tauri-apps/api/app";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { listen } from "@tauri-apps/api/event";
-import { WebSocketMessage } from "@/types/websocket";
+import { WebSocketMessage, WebSocketResponse } from "@/types/websocket";
+import { Config } from "@/types/settings";
 import { useNavigate } from "react-router-dom";
 import { platform } from "@tauri-apps/plugin-os";
 import { useMacOsRegisterer } from "@/helpers/use-macos-registerer";
@@ -28,6 +29,9 @@ import useDownloader from "@/helpers/use-downloader";
 import usePotServer from "@/helpers/use-pot-server";
 import { useLinuxRegisterer } from "@/helpers/use-linux-registerer";
 import { invoke } from "@tauri-apps/api/core";
+import { sendNotification } from "@tauri-apps/plugin-notification";
+import { useThrottledCallback } from "@tanstack/react-pacer/throttler";
+import { DownloadFinishedEvent, DownloadProgressEvent } from "@/types/download";
 
 
 export default function App({ children }: { children: React.ReactNode }) {
@@ -65,6 +69,7 @@ export default function App({ children }: { children: React.ReactNode }) {
 
     const erroredDownloadIds = useDownloaderPageStatesStore((state) => state.erroredDownloadIds);
     const expectedErrorDownloadIds = useDownloaderPageStatesStore((state) => state.expectedErrorDownloadIds);
+    const addErroredDownload = useDownloaderPageStatesStore((state) => state.addErroredDownload);
     const removeErroredDownload = useDownloaderPageStatesStore((state) => state.removeErroredDownload);
     const removeExpectedErrorDownload = useDownloaderPageStatesStore((state) => state.removeExpectedErrorDownload);
 
@@ -85,9 +90,6 @@ export default function App({ children }: { children: React.ReactNode }) {
     const queryClient = useQueryClient();
     const downloadStatusUpdater = useUpdateDownloadStatus();
 
-    const ongoingDownloads = globalDownloadStates.filter(state => state.download_status === 'downloading' || state.download_status === 'starting');
-    const queuedDownloads = globalDownloadStates.filter(state => state.download_status === 'queued').sort((a, b) => a.queue_index! - b.queue_index!);
-
     const hasRunYtDlpAutoUpdateRef = useRef(false);
     const hasRunAppUpdateCheckRef = useRef(false);
     const hasRunPotServerStatusCheckRef = useRef(false);
@@ -95,25 +97,7 @@ export default function App({ children }: { children: React.ReactNode }) {
     const isRegisteredToLinuxRef = useRef(false);
     const pendingErrorUpdatesRef = useRef<Set<string>>(new Set());
 
-    const { fetchVideoMetadata, startDownload, pauseDownload, resumeDownload, cancelDownload, processQueuedDownloads } = useDownloader();
-
-    const ongoingDownloadsCloseable = globalDownloadStates.filter(state => ['starting', 'downloading', 'queued'].includes(state.download_status));
-    const setIsPausingDownload = useDownloadActionStatesStore(state => state.setIsPausingDownload);
-
-    const stopOngoingDownloads = async () => {
-        if (ongoingDownloadsCloseable.length > 0) {
-            for (const state of ongoingDownloadsCloseable) {
-                setIsPausingDownload(state.download_id, true);
-                try {
-                    await pauseDownload(state);
-                } catch (e) {
-                    console.error(e);
-                } finally {
-                    setIsPausingDownload(state.download_id, false);
-                }
-            }
-        }
-    }
+    const { fetchVideoMetadata, startDownload, pauseDownload, resumeDownload, cancelDownload } = useDownloader();
 
     // Prevent right click context menu in production
     if (!import.meta.env.DEV) {
@@ -127,9 +111,7 @@ export default function App({ children }: { children: React.ReactNode }) {
         const handleCloseRequested = async (event: any) => {
             event.preventDefault();
             if (QUIT_ON_CLOSE) {
-                if (ongoingDownloads.length > 0) {
-                    await stopOngoingDownloads()
-                }
+                // Ongoing downloads are paused by the backend before the app exits
                 await appWindow.destroy();
             } else {
                 await appWindow.hide();
@@ -172,25 +154,172 @@ export default function App({ children }: { children: React.ReactNode }) {
         detectEnvironment();
     }, [setIsFlatpak, setIsAppimage, setAppDirPath]);
 
+    // Warn when the config file could not be loaded and a backup or the defaults are used
+    useEffect(() => {
+        invoke<string | null>('get_config_load_error').then((error) => {
+            if (error) {
+                LOG.error('NEODLP', `Failed to load config: ${error}`);
+                toast.warning("Failed to load config", {
+                    description: error,
+                });
+            }
+        }).catch((error) => {
+            console.error('Failed to check config load error:', error);
+        });
+    }, []);
+
+    // The websocket listener is registered once, keep it pointed at the latest downloader functions
+    const downloaderRef = useRef({ fetchVideoMetadata, startDownload, cancelDownload });
+    downloaderRef.current = { fetchVideoMetadata, startDownload, cancelDownload };
+
     // Listen for websocket messages
     useEffect(() => {
         const unlisten = listen<WebSocketMessage>('websocket-message', (event) => {
-        if(event.payload.command === 'download') {
-            const handleDownload = async () => {
-                appWindow.show();
-                appWindow.setFocus();
-                navigate('/');
-                if (event.payload.url) {
-                    LOG.info('NEODLP', `Received search request from neodlp browser extension for URL: ${event.payload.url}`);
-                    const { setRequestedUrl, setAutoSubmitSearch } = useCurrentVideoMetadataStore.getState();
-                    setRequestedUrl(event.payload.url);
-                    setAutoSubmitSearch(true);
+        const message = event.payload;
+        // Every message carries a request id, the backend routes the response to the extension that sent it
+        const respond = (response: WebSocketResponse) => {
+            invoke('receive_frontend_response', { response: JSON.stringify(response), requestId: message.request_id }).catch((error) => {
+                console.error("Error responding to websocket message:", error);
+            });
+        }
+        const handleMessage = async (): Promise<WebSocketResponse> => {
+            const { fetchVideoMetadata, startDownload, cancelDownload } = downloaderRef.current;
+            switch (message.command) {
+                case 'download': {
+                    appWindow.show();
+                    appWindow.setFocus();
+                    navigate('/');
+                    if (message.url) {
+                        LOG.info('NEODLP', `Received search request from neodlp browser extension for URL: ${message.url}`);
+                        const { setRequestedUrl, setAutoSubmitSearch } = useCurrentVideoMetadataStore.getState();
+                        setRequestedUrl(message.url);
+                        setAutoSubmitSearch(true);
+                    }
+                    return { type: 'accepted' };
+                }
+                case 'enqueue': {
+                    LOG.info('NEODLP', `Received enqueue request from neodlp browser extension for URL: ${message.url}`);
+                    const { downloadConfiguration } = useDownloaderPageStatesStore.getState();
+                    await startDownload({ url: message.url, selectedFormat: message.format || 'best', downloadConfig: downloadConfiguration });
+                    return { type: 'accepted' };
+                }
+                case 'fetch_info': {
+                    const info = await fetchVideoMetadata({ url: message.url });
+                    if (!info) return { type: 'error', code: 'internal', message: `Failed to fetch info for URL: ${message.url}` };
+                    return { type: 'info', info };
                 }
+                case 'cancel': {
+                    const downloadState = useDownloadStatesStore.getState().downloadStates.find((state) => state.download_id === message.download_id);
+                    if (!downloadState) return { type: 'error', code: 'not_found', message: `Download ${message.download_id} not found` };
+                    await cancelDownload(downloadState);
+                    return { type: 'cancelled', download_id: message.download_id };
+                }
+                default:
+                    return { type: 'error', code: 'unknown_command', message: `Unknown command: ${(message as { command: string }).command}` };
             }
-            handleDownload().catch((error) => {
-                console.error("Error handling download:", error);
-            });
         }
+        handleMessage().then(respond).catch((error) => {
+            console.error("Error handling websocket message:", error);
+            respond({ type: 'error', code: 'internal', message: String(error) });
+        });
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    // Progress is written to the database by the backend, only refresh the download states
+    const refreshDownloadStates = useThrottledCallback(() => {
+        queryClient.invalidateQueries({ queryKey: ['download-states'] });
+    }, { key: 'refresh-download-states', wait: 500 });
+    const refreshDownloadStatesRef = useRef(refreshDownloadStates);
+    refreshDownloadStatesRef.current = refreshDownloadStates;
+
+    // Listen for progress of the yt-dlp downloads run by the backend
+    useEffect(() => {
+        const unlisten = listen<DownloadProgressEvent>('download-progress', (event) => {
+            const { download_id, status, progress, speed, downloaded, total, eta } = event.payload;
+            const { debug_mode, log_progress } = useSettingsPageStatesStore.getState().settings;
+            if (debug_mode && log_progress) {
+                LOG.progress(`YT-DLP Download ${download_id}`, `status:${status},progress:${progress}%,speed:${speed},downloaded:${downloaded},total:${total},eta:${eta}`);
+            }
+            refreshDownloadStatesRef.current();
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    // Listen for yt-dlp downloads run by the backend exiting, their final status is already saved
+    useEffect(() => {
+        const unlisten = listen<DownloadFinishedEvent>('download-finished', (event) => {
+            const { download_id, exit_code, download_status } = event.payload;
+            queryClient.invalidateQueries({ queryKey: ['download-states'] });
+
+            if (download_status === 'errored') {
+                LOG.error(`YT-DLP Download ${download_id}`, `yt-dlp exited with code ${exit_code}`);
+                addErroredDownload(download_id);
+                return;
+            }
+            LOG.info(`YT-DLP Download ${download_id}`, `yt-dlp exited with code ${exit_code}`);
+            if (download_status !== 'completed') return;
+
+            LOG.info('NEODLP', `yt-dlp download completed with id: ${download_id}`);
+            const downloadState = useDownloadStatesStore.getState().downloadStates.find(d => d.download_id === download_id);
+            const isMultiplePlaylistItems = downloadState?.playlist_id && downloadState?.playlist_indices && downloadState.playlist_indices.includes(',');
+            const description = `The download for ${isMultiplePlaylistItems ? 'playlist ' : ''}"${isMultiplePlaylistItems ? downloadState?.playlist_title : downloadState?.title}" has completed successfully.`;
+            toast.success("Download Completed", { description });
+
+            const { enable_notifications, download_completion_notification } = useSettingsPageStatesStore.getState().settings;
+            if (enable_notifications && download_completion_notification) {
+                sendNotification({
+                    title: "Download Completed",
+                    body: description,
+                });
+            }
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    // The backend applied an edit of the config file and updated the matching settings
+    useEffect(() => {
+        const unlisten = listen<Config>('config-changed', () => {
+            LOG.info('NEODLP', 'Config file changed, reloading settings');
+            queryClient.invalidateQueries({ queryKey: ['settings'] });
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    // An edit of the config file was rejected, the backend keeps using the previous config
+    useEffect(() => {
+        const unlisten = listen<string>('config-load-failed', (event) => {
+            LOG.error('NEODLP', `Failed to reload config: ${event.payload}`);
+            toast.error("Failed to reload config", {
+                description: event.payload,
+            });
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    // The configured websocket port was taken and the server moved to another one
+    useEffect(() => {
+        const unlisten = listen<{ requested_port: number; port: number }>('websocket-port-fallback', (event) => {
+            const { requested_port, port } = event.payload;
+            LOG.warning('NEODLP', `Websocket port ${requested_port} is in use, switched to port ${port}`);
+            toast.info("Websocket port changed", {
+                description: `Port ${requested_port} is in use, the extension now connects on port ${port}`,
+            });
         });
 
         return () => {
@@ -478,17 +607,6 @@ export default function App({ children }: { children: React.ReactNode }) {
         }
     }, [downloadStates, isSuccessFetchingDownloadStates, setDownloadStates]);
 
-    // Process queued downloads
-    useEffect(() => {
-        // Safely process the queue when dependencies change
-        const timeoutId = setTimeout(() => {
-            processQueuedDownloads();
-        }, 500);
-
-        // Cleanup timeout if component unmounts or dependencies change
-        return () => clearTimeout(timeoutId);
-    }, [processQueuedDownloads, ongoingDownloads, queuedDownloads]);
-
     // show a toast and pause the download when yt-dlp exits unexpectedly
     useEffect(() => {
         const unexpectedErrors = Array.from(erroredDownloadIds).filter(id => !expectedErrorDownloadIds.has(id));
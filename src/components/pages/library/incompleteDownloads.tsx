@@ -93,7 +93,7 @@ export function IncompleteDownload({ state }: IncompleteDownloadProps) {
                     {((state.download_status === 'starting') || (state.download_status === 'downloading' && state.status === 'finished')) && (
                         <IndeterminateProgress indeterminate={true} className="w-full" />
                     )}
-                    {(state.download_status === 'downloading' || state.download_status === 'paused' || state.download_status === 'errored') && state.progress && state.status !== 'finished' && (
+                    {(state.download_status === 'downloading' || state.download_status === 'paused' || state.download_status === 'interrupted' || state.download_status === 'errored') && state.progress && state.status !== 'finished' && (
                         <div className="w-full flex items-center gap-2">
                             {isMultiplePlaylistItems && state.item ? (
                                 <span className="text-sm text-nowrap">({state.item})</span>
@@ -126,7 +126,7 @@ export function IncompleteDownload({ state }: IncompleteDownloadProps) {
                     </div>
                 </div>
                 <div className="w-full flex items-center gap-2 mt-2">
-                    {state.download_status === 'paused' ? (
+                    {(state.download_status === 'paused' || state.download_status === 'interrupted') ? (
                         <Button
                         size="sm"
                         className="w-fill"
@@ -131,6 +131,8 @@ function AppGeneralSettings() {
     const preferVideoOverPlaylist = useSettingsPageStatesStore(state => state.settings.prefer_video_over_playlist);
     const strictDownloadabilityCheck = useSettingsPageStatesStore(state => state.settings.strict_downloadablity_check);
     const quitOnClose = useSettingsPageStatesStore(state => state.settings.quit_on_close);
+    const autoResumeDownloads = useSettingsPageStatesStore(state => state.settings.auto_resume_downloads);
+    const shutdownTimeout = useSettingsPageStatesStore(state => state.settings.shutdown_timeout);
     const useAria2 = useSettingsPageStatesStore(state => state.settings.use_aria2);
     const useCustomCommands = useSettingsPageStatesStore(state => state.settings.use_custom_commands);
 
@@ -189,6 +191,28 @@ function AppGeneralSettings() {
             onCheckedChange={(checked) => saveSettingsKey('quit_on_close', checked)}
             />
         </div>
+        <div className="auto-resume-downloads">
+            <h3 className="font-semibold">Auto Resume Downloads</h3>
+            <p className="text-xs text-muted-foreground mb-3">Wheather to automatically resume the downloads that were paused by quitting the app on next startup (downloads paused manually are not affected)</p>
+            <Switch
+            id="auto-resume-downloads"
+            checked={autoResumeDownloads}
+            onCheckedChange={(checked) => saveSettingsKey('auto_resume_downloads', checked)}
+            />
+        </div>
+        <div className="shutdown-timeout">
+            <h3 className="font-semibold">Shutdown Timeout</h3>
+            <p className="text-xs text-muted-foreground mb-3">Set how many seconds ongoing downloads get to save their progress when quitting the app, before they are forcefully stopped</p>
+            <Slider
+            id="shutdown-timeout"
+            className="w-87.5 mb-2"
+            value={[shutdownTimeout]}
+            min={1}
+            max={60}
+            onValueChange={(value) => saveSettingsKey('shutdown_timeout', value[0])}
+            />
+            <Label htmlFor="shutdown-timeout" className="text-xs text-muted-foreground">(Current: {shutdownTimeout}s) (Default: 10s, Maximum: 60s)</Label>
+        </div>
         <div className="aria2">
             <h3 className="font-semibold">Aria2</h3>
             <p className="text-xs text-muted-foreground mb-3">Use aria2c as external downloader (recommended only if you are experiancing too slow download speeds with native downloader, you need to install aria2 via homebrew if you are on macos to use this feature)</p>
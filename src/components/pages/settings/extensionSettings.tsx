@@ -1,11 +1,12 @@
-import { useEffect } from "react";
+import { useEffect, useState } from "react";
 import { Card } from "@/components/ui/card";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import { useEnvironmentStore, useSettingsPageStatesStore } from "@/services/store";
 import { Label } from "@/components/ui/label";
 import { Button } from "@/components/ui/button";
+import { Switch } from "@/components/ui/switch";
 import { toast } from "sonner";
-import { ArrowDownToLine, ArrowRight, EthernetPort, Loader2, Radio, RotateCw } from "lucide-react";
+import { Activity, ArrowDownToLine, ArrowRight, EthernetPort, Loader2, Radio, RotateCw } from "lucide-react";
 import { useSettings } from "@/helpers/use-settings";
 import { z } from "zod";
 import { useForm } from "react-hook-form";
@@ -16,6 +17,8 @@ import { SlidingButton } from "@/components/custom/slidingButton";
 import clsx from "clsx";
 import { NumberInput } from "@/components/custom/numberInput";
 import { platform } from "@tauri-apps/plugin-os";
+import { WebSocketStats } from "@/types/websocket";
+import { Config, ConfigInfo } from "@/types/settings";
 
 const websocketPortSchema = z.object({
     port: z.coerce.number<number>({
@@ -107,15 +110,13 @@ function ExtPortSettings() {
     const acknowledgeFormReset = useSettingsPageStatesStore(state => state.acknowledgeFormReset);
 
     const websocketPort = useSettingsPageStatesStore(state => state.settings.websocket_port);
+    const websocketPortFallback = useSettingsPageStatesStore(state => state.settings.websocket_port_fallback);
     const isChangingWebSocketPort = useSettingsPageStatesStore(state => state.isChangingWebSocketPort);
     const setIsChangingWebSocketPort = useSettingsPageStatesStore(state => state.setIsChangingWebSocketPort);
     const isRestartingWebSocketServer = useSettingsPageStatesStore(state => state.isRestartingWebSocketServer);
 
     const { saveSettingsKey } = useSettings();
-
-    interface Config {
-        port: number;
-    }
+    const [effectivePort, setEffectivePort] = useState<ConfigInfo['effective']['port'] | null>(null);
 
     const websocketPortForm = useForm<z.infer<typeof websocketPortSchema>>({
         resolver: zodResolver(websocketPortSchema),
@@ -131,12 +132,12 @@ function ExtPortSettings() {
         setIsChangingWebSocketPort(true);
         try {
             // const port = parseInt(values.port, 10);
-            const updatedConfig: Config = await invoke("update_config", {
-                newConfig: {
-                    port: values.port,
+            const updatedConfig: Config = await invoke("patch_config", {
+                patch: {
+                    extension: { port: values.port },
                 }
             });
-            saveSettingsKey('websocket_port', updatedConfig.port);
+            saveSettingsKey('websocket_port', updatedConfig.extension.port);
             toast.success("Websocket port updated", {
                 description: `Websocket port changed to ${values.port}`,
             });
@@ -150,6 +151,14 @@ function ExtPortSettings() {
         }
     }
 
+    useEffect(() => {
+        invoke<ConfigInfo>("get_config").then((config) => {
+            setEffectivePort(config.effective.port);
+        }).catch((error) => {
+            console.error("Error fetching config:", error);
+        });
+    }, [websocketPort]);
+
     useEffect(() => {
         if (formResetTrigger > 0) {
             websocketPortForm.reset();
@@ -158,6 +167,7 @@ function ExtPortSettings() {
     }, [formResetTrigger]);
 
     return (
+        <>
         <div className="websocket-port">
             <h3 className="font-semibold">Websocket Port</h3>
             <p className="text-xs text-muted-foreground mb-3">Change extension websocket server port</p>
@@ -178,7 +188,7 @@ function ExtPortSettings() {
                                         {...field}
                                         />
                                     </FormControl>
-                                    <Label htmlFor="port" className="text-xs text-muted-foreground">(Current: {websocketPort}) (Default: 53511, Range: 50000-60000)</Label>
+                                    <Label htmlFor="port" className="text-xs text-muted-foreground">(Current: {websocketPort}) (Default: 53511, Range: 50000-60000){effectivePort?.source === 'environment' ? ` (Overridden by NEODLP_WS_PORT: ${effectivePort.value})` : ''}{effectivePort?.source === 'fallback' ? ` (In use, fell back to: ${effectivePort.value})` : ''}</Label>
                                     <FormMessage />
                                 </FormItem>
                             )}
@@ -200,6 +210,69 @@ function ExtPortSettings() {
                 </Form>
             </div>
         </div>
+        <div className="websocket-port-fallback">
+            <h3 className="font-semibold">Port Fallback</h3>
+            <p className="text-xs text-muted-foreground mb-3">Wheather to switch to the first free port of the fallback range (set in the config file) when the websocket port is already in use, the extension finds the new port automatically</p>
+            <Switch
+            id="websocket-port-fallback"
+            checked={websocketPortFallback}
+            onCheckedChange={(checked) => saveSettingsKey('websocket_port_fallback', checked)}
+            />
+        </div>
+        </>
+    );
+}
+
+const websocketStatsLabels: { key: keyof WebSocketStats; label: string }[] = [
+    { key: "connections", label: "Connections" },
+    { key: "handshake_failures", label: "Failed handshakes" },
+    { key: "rejected_origins", label: "Rejected origins" },
+    { key: "unauthorized", label: "Unauthorized clients" },
+    { key: "invalid_messages", label: "Invalid messages" },
+    { key: "emit_failures", label: "Undelivered requests" },
+    { key: "timeouts", label: "Timed out requests" },
+];
+
+function ExtStatsSettings() {
+    const [stats, setStats] = useState<WebSocketStats | null>(null);
+    const [isLoadingStats, setIsLoadingStats] = useState(false);
+
+    const loadStats = async () => {
+        setIsLoadingStats(true);
+        try {
+            setStats(await invoke<WebSocketStats>("get_websocket_stats"));
+        } catch (error) {
+            console.error("Error fetching websocket stats:", error);
+        } finally {
+            setIsLoadingStats(false);
+        }
+    }
+
+    useEffect(() => {
+        loadStats();
+    }, []);
+
+    return (
+        <div className="websocket-stats">
+            <h3 className="font-semibold">Diagnostics</h3>
+            <p className="text-xs text-muted-foreground mb-3">Websocket server activity since NeoDLP was launched</p>
+            <div className="flex flex-col gap-1 mb-4">
+                {websocketStatsLabels.map((item) => (
+                    <div key={item.key} className="flex items-center justify-between text-sm">
+                        <span className="text-muted-foreground">{item.label}</span>
+                        <span className="font-semibold">{stats ? stats[item.key] : '-'}</span>
+                    </div>
+                ))}
+            </div>
+            <Button variant="outline" onClick={loadStats} disabled={isLoadingStats}>
+                {isLoadingStats ? (
+                    <Loader2 className="h-4 w-4 animate-spin" />
+                ) : (
+                    <RotateCw className="h-4 w-4" />
+                )}
+                Refresh
+            </Button>
+        </div>
     );
 }
 
@@ -214,6 +287,7 @@ export function ExtensionSettings() {
     const tabsList = [
         { key: "install", label: "Install", icon: ArrowDownToLine, component: <ExtInstallSettings /> },
         { key: "port", label: "Port", icon: EthernetPort, component: <ExtPortSettings /> },
+        { key: "diagnostics", label: "Diagnostics", icon: Activity, component: <ExtStatsSettings /> },
     ];
 
     return (
@@ -4,36 +4,13 @@ import { MaximizeIcon } from "@/components/icons/maximize";
 import { MinimizeIcon } from "@/components/icons/minimize";
 import { CloseIcon } from "@/components/icons/close";
 import { UnmaximizeIcon } from "@/components/icons/unmaximize";
-import { useDownloadActionStatesStore, useDownloadStatesStore, useSettingsPageStatesStore } from "@/services/store";
-import { useAppContext } from "@/providers/appContextProvider";
+import { useSettingsPageStatesStore } from "@/services/store";
 
 export default function TitleBar() {
     const [maximized, setMaximized] = useState<boolean>(false);
     const appWindow = getCurrentWebviewWindow();
     const quitOnClose = useSettingsPageStatesStore(state => state.settings.quit_on_close);
 
-    const downloadStates = useDownloadStatesStore(state => state.downloadStates);
-    const ongoingDownloads = downloadStates.filter(state =>
-        ['starting', 'downloading', 'queued'].includes(state.download_status)
-    );
-    const setIsPausingDownload = useDownloadActionStatesStore(state => state.setIsPausingDownload);
-    const { pauseDownload } = useAppContext();
-
-    const stopOngoingDownloads = async () => {
-        if (ongoingDownloads.length > 0) {
-            for (const state of ongoingDownloads) {
-                setIsPausingDownload(state.download_id, true);
-                try {
-                    await pauseDownload(state);
-                } catch (e) {
-                    console.error(e);
-                } finally {
-                    setIsPausingDownload(state.download_id, false);
-                }
-            }
-        }
-    }
-
     return (
         <div className="titlebar flex items-center justify-between border-b bg-background">
             <div className="flex items-center justify-center grow px-4 py-2.5" data-tauri-drag-region>
@@ -75,9 +52,7 @@ export default function TitleBar() {
                 title="Close"
                 onClick={async () => {
                     if (quitOnClose) {
-                        if (ongoingDownloads.length > 0) {
-                            await stopOngoingDownloads();
-                        }
+                        // Ongoing downloads are paused by the backend before the app exits
                         await appWindow.destroy();
                     } else {
                         await appWindow.hide()
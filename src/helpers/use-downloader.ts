@@ -1,25 +1,17 @@
 import { DownloadState } from "@/types/download";
-import { invoke } from "@tauri-apps/api/core";
-import { useCallback, useRef } from "react";
-import { useBasePathsStore, useCurrentVideoMetadataStore, useDownloaderPageStatesStore, useDownloadStatesStore, useSettingsPageStatesStore } from "@/services/store";
-import { determineFileType, extractPlaylistItemProgress, generateVideoId, parseProgressLine } from "@/utils";
-import { Command } from "@tauri-apps/plugin-shell";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { useBasePathsStore, useCurrentVideoMetadataStore, useDownloaderPageStatesStore, useSettingsPageStatesStore } from "@/services/store";
+import { determineFileType, generateVideoId } from "@/utils";
 import { RawVideoInfo } from "@/types/video";
-import { useDeleteDownloadState, useSaveDownloadState, useSavePlaylistInfo, useSaveVideoInfo, useUpdateDownloadFilePath, useUpdateDownloadPlaylistItem, useUpdateDownloadStatus } from "@/services/mutations";
+import { useDeleteDownloadState, useSaveDownloadState, useSavePlaylistInfo, useSaveVideoInfo } from "@/services/mutations";
 import { useQueryClient } from "@tanstack/react-query";
 import { platform } from "@tauri-apps/plugin-os";
 import { toast } from "sonner";
 import { useLogger } from "@/helpers/use-logger";
 import { ulid } from "ulid";
-import { sendNotification } from '@tauri-apps/plugin-notification';
 import { FetchVideoMetadataParams, StartDownloadParams } from "@/providers/appContextProvider";
-import { useThrottledCallback } from '@tanstack/react-pacer/throttler';
-import { fetchDownloadStateById } from "@/services/database";
-import { dataDir } from "@tauri-apps/api/path";
 
 export default function useDownloader() {
-    const globalDownloadStates = useDownloadStatesStore((state) => state.downloadStates);
-
     const ffmpegPath = useBasePathsStore((state) => state.ffmpegPath);
     const tempDownloadDirPath = useBasePathsStore((state) => state.tempDownloadDirPath);
     const downloadDirPath = useBasePathsStore((state) => state.downloadDirPath);
@@ -27,7 +19,6 @@ export default function useDownloader() {
     const setSearchPid = useCurrentVideoMetadataStore((state) => state.setSearchPid);
 
     const {
-        max_parallel_downloads: MAX_PARALLEL_DOWNLOADS,
         max_retries: MAX_RETRIES,
         prefer_video_over_playlist: PREFER_VIDEO_OVER_PLAYLIST,
         strict_downloadablity_check: STRICT_DOWNLOADABILITY_CHECK,
@@ -61,8 +52,6 @@ export default function useDownloader() {
         debug_mode: DEBUG_MODE,
         log_verbose: LOG_VERBOSE,
         log_progress: LOG_PROGRESS,
-        enable_notifications: ENABLE_NOTIFICATIONS,
-        download_completion_notification: DOWNLOAD_COMPLETION_NOTIFICATION,
         use_delay: USE_DELAY,
         use_search_delay: USE_SEARCH_DELAY,
         delay_mode: DELAY_MODE,
@@ -79,10 +68,8 @@ export default function useDownloader() {
     } = useSettingsPageStatesStore(state => state.settings);
     const isRunningPotServer = useSettingsPageStatesStore(state => state.isRunningPotServer);
 
-    const expectedErrorDownloadIds = useDownloaderPageStatesStore((state) => state.expectedErrorDownloadIds);
     const addErroredDownload = useDownloaderPageStatesStore((state) => state.addErroredDownload);
     const removeErroredDownload = useDownloaderPageStatesStore((state) => state.removeErroredDownload);
-    const addExpectedErrorDownload = useDownloaderPageStatesStore((state) => state.addExpectedErrorDownload);
     const removeExpectedErrorDownload = useDownloaderPageStatesStore((state) => state.removeExpectedErrorDownload);
 
     const LOG = useLogger();
@@ -90,31 +77,10 @@ export default function useDownloader() {
 
     const queryClient = useQueryClient();
     const downloadStateSaver = useSaveDownloadState();
-    const downloadStatusUpdater = useUpdateDownloadStatus();
-    const downloadFilePathUpdater = useUpdateDownloadFilePath();
-    const playlistItemUpdater = useUpdateDownloadPlaylistItem();
     const videoInfoSaver = useSaveVideoInfo();
     const downloadStateDeleter = useDeleteDownloadState();
     const playlistInfoSaver = useSavePlaylistInfo();
 
-    const ongoingDownloads = globalDownloadStates.filter(state => state.download_status === 'downloading' || state.download_status === 'starting');
-    const queuedDownloads = globalDownloadStates.filter(state => state.download_status === 'queued').sort((a, b) => a.queue_index! - b.queue_index!);
-
-    const isProcessingQueueRef = useRef(false);
-    const lastProcessedDownloadIdRef = useRef<string | null>(null);
-
-    const updateDownloadProgress = useThrottledCallback((state: DownloadState) => {
-        downloadStateSaver.mutate(state, {
-            onSuccess: (_data) => {
-                // console.log("Download State saved successfully:", data);
-                queryClient.invalidateQueries({ queryKey: ['download-states'] });
-            },
-            onError: (error) => {
-                console.error("Failed to save download state:", error);
-            }
-        });
-    }, { key: 'update-download-progress', wait: 500 });
-
     const fetchVideoMetadata = async (params: FetchVideoMetadataParams): Promise<RawVideoInfo | null> => {
         const { url, formatId, playlistIndices, selectedSubtitles, resumeState, downloadConfig } = params;
         try {
@@ -200,69 +166,12 @@ export default function useDownloader() {
                 }
             }
 
-            const isFlatpak = await invoke<boolean>('is_flatpak');
-            const xdgDataDir = await dataDir();
-            const spawnOpts = { env: { PYTHONUNBUFFERED: '1' } };
-            const command = isFlatpak
-            ? Command.create('sh', ['-c', `${xdgDataDir}/yt-dlp/yt-dlp ${args.map(arg => `'${arg.replace(/'/g, "'\\''")}'`).join(' ')}`], spawnOpts)
-            : Command.sidecar('binaries/yt-dlp', args, spawnOpts);
-
-            let jsonOutput = '';
-
-            return new Promise<RawVideoInfo | null>((resolve) => {
-                command.stdout.on('data', line => {
-                    if (line.startsWith('{')) {
-                        jsonOutput = line;
-                    } else {
-                        if (line.trim() !== '') LOG.info('YT-DLP', line);
-                    }
-                });
-
-                command.stderr.on('data', line => {
-                    if (line.trim() !== '') LOG.info('YT-DLP', line);
-                });
-
-                command.on('close', async (data) => {
-                    if (data.code !== 0) {
-                        console.error(`yt-dlp exited with code ${data.code} while fetching metadata for URL: ${url}`);
-                        LOG.error('NEODLP', `yt-dlp exited with code ${data.code} while fetching metadata for URL: ${url} (ignore if you manually cancelled)`);
-                    } else {
-                        LOG.info('NEODLP', `yt-dlp exited with code ${data.code} while fetching metadata for URL: ${url}`);
-                    }
-
-                    try {
-                        const matchedJson = jsonOutput.match(/{.*}/);
-                        if (!matchedJson) {
-                            console.error(`Failed to match JSON: ${jsonOutput}`);
-                            LOG.error('NEODLP', `Failed to parse metadata JSON for URL: ${url})`);
-                            resolve(null);
-                            return;
-                        }
-                        const parsedJson: RawVideoInfo = JSON.parse(matchedJson[0]);
-                        resolve(parsedJson);
-                    }
-                    catch (e) {
-                        console.error(`Failed to parse JSON: ${e}`);
-                        LOG.error('NEODLP', `Failed to parse metadata JSON for URL: ${url}) with error: ${e}`);
-                        resolve(null);
-                    }
-                });
-
-                command.on('error', error => {
-                    console.error(`Error fetching metadata: ${error}`);
-                    LOG.error('NEODLP', `Error occurred while fetching metadata for URL: ${url} : ${error}`);
-                    resolve(null);
-                });
-
-                LOG.info('NEODLP', `Fetching metadata for URL: ${url}, with args: ${args.join(' ')}`);
-                command.spawn().then(child => {
-                    setSearchPid(child.pid);
-                }).catch(e => {
-                    console.error(`Failed to spawn command: ${e}`);
-                    LOG.error('NEODLP', `Failed to spawn yt-dlp process for fetching metadata for URL: ${url} : ${e}`);
-                    resolve(null);
-                });
-            });
+            LOG.info('NEODLP', `Fetching metadata for URL: ${url}, with args: ${args.join(' ')}`);
+            const onSpawn = new Channel<number>();
+            onSpawn.onmessage = (pid) => setSearchPid(pid);
+            const metadata = await invoke<RawVideoInfo>('fetch_video_metadata', { args, onSpawn });
+            LOG.info('NEODLP', `Fetched metadata for URL: ${url}`);
+            return metadata;
         } catch (e) {
             console.error(`Failed to fetch metadata: ${e}`);
             LOG.error('NEODLP', `Failed to fetch metadata for URL: ${url} : ${e}`);
@@ -327,8 +236,6 @@ export default function useDownloader() {
         // const tempDownloadPathForYtdlp = await join(tempDownloadDirPath, `${downloadId}_${selectedFormat}.%(ext)s`);
         // const tempDownloadPath = await join(tempDownloadDirPath, `${downloadId}_${selectedFormat}.${videoMetadata.ext}`);
         // let downloadFilePath = resumeState?.filepath || await join(downloadDirPath, sanitizeFilename(`${videoMetadata.title}_${videoMetadata.resolution || 'unknown'}[${videoMetadata.id}].${videoMetadata.ext}`));
-        let downloadFilePath: string | null = null;
-        let processPid: number | null = null;
         const args = [
             url,
             '--newline',
@@ -481,31 +388,31 @@ export default function useDownloader() {
             }
         }
 
-        let embedMetadata = 0;
+        let embedMetadata = false;
         if ((!USE_CUSTOM_COMMANDS && !resumeState?.custom_command) && (downloadConfig.embed_metadata || resumeState?.embed_metadata || EMBED_VIDEO_METADATA || EMBED_AUDIO_METADATA)) {
             const shouldEmbedMetaForVideo = (fileType === 'video+audio' || fileType === 'video') && (downloadConfig.embed_metadata || resumeState?.embed_metadata || (EMBED_VIDEO_METADATA && downloadConfig.embed_metadata === null));
             const shouldEmbedMetaForAudio = fileType === 'audio' && (downloadConfig.embed_metadata || resumeState?.embed_metadata || (EMBED_AUDIO_METADATA && downloadConfig.embed_metadata === null));
             const shouldEmbedMetaForUnknown = fileType === 'unknown' && (downloadConfig.embed_metadata || resumeState?.embed_metadata);
 
             if (shouldEmbedMetaForUnknown || shouldEmbedMetaForVideo || shouldEmbedMetaForAudio) {
-                embedMetadata = 1;
+                embedMetadata = true;
                 args.push('--embed-metadata');
             }
         }
 
-        let embedThumbnail = 0;
-        let squareCropThumbnail = 0;
+        let embedThumbnail = false;
+        let squareCropThumbnail = false;
         if ((!USE_CUSTOM_COMMANDS && !resumeState?.custom_command) && (downloadConfig.embed_thumbnail || resumeState?.embed_thumbnail || EMBED_VIDEO_THUMBNAIL || EMBED_AUDIO_THUMBNAIL)) {
             const shouldEmbedThumbForVideo = (fileType === 'video+audio' || fileType === 'video') && (downloadConfig.embed_thumbnail || resumeState?.embed_thumbnail || (EMBED_VIDEO_THUMBNAIL && downloadConfig.embed_thumbnail === null));
             const shouldEmbedThumbForAudio = fileType === 'audio' && (downloadConfig.embed_thumbnail || resumeState?.embed_thumbnail || (EMBED_AUDIO_THUMBNAIL && downloadConfig.embed_thumbnail === null));
             const shouldEmbedThumbForUnknown = fileType === 'unknown' && (downloadConfig.embed_thumbnail || resumeState?.embed_thumbnail);
 
             if (shouldEmbedThumbForUnknown || shouldEmbedThumbForVideo || shouldEmbedThumbForAudio) {
-                embedThumbnail = 1;
+                embedThumbnail = true;
                 args.push('--embed-thumbnail', '--convert-thumbnail', 'jpg');
 
                 if (downloadConfig.square_crop_thumbnail || resumeState?.square_crop_thumbnail) {
-                    squareCropThumbnail = 1;
+                    squareCropThumbnail = true;
                     args.push('--postprocessor-args', 'ThumbnailsConvertor+FFmpeg_o:-c:v mjpeg -qmin 1 -qscale:v 1 -vf crop="\'min(iw,ih)\':\'min(iw,ih)\'"');
                 }
             }
@@ -551,9 +458,9 @@ export default function useDownloader() {
             }
         }
 
-        let useAria2 = 0;
+        let useAria2 = false;
         if ((!USE_CUSTOM_COMMANDS && !resumeState?.custom_command) && (USE_ARIA2 || resumeState?.use_aria2)) {
-            useAria2 = 1;
+            useAria2 = true;
             args.push(
                 '--downloader', 'aria2c',
                 '--downloader', 'dash,m3u8:native',
@@ -579,200 +486,60 @@ export default function useDownloader() {
             args.push('--no-continue');
         }
 
-        console.log('Starting download with args:', args);
-        const isFlatpak = await invoke<boolean>('is_flatpak');
-        const xdgDataDir = await dataDir();
-        const spawnOpts = { env: { PYTHONUNBUFFERED: '1' } };
-        const command = isFlatpak
-        ? Command.create('sh', ['-c', `${xdgDataDir}/yt-dlp/yt-dlp ${args.map(arg => `'${arg.replace(/'/g, "'\\''")}'`).join(' ')}`], spawnOpts)
-        : Command.sidecar('binaries/yt-dlp', args, spawnOpts);
-
-        command.on('close', async (data) => {
-            if (data.code !== 0) {
-                console.error(`Download failed with code ${data.code}`);
-                LOG.error(`YT-DLP Download ${downloadId}`, `yt-dlp exited with code ${data.code} (ignore if you manually paused or cancelled the download)`);
-                if (!expectedErrorDownloadIds.has(downloadId)) addErroredDownload(downloadId);
-            } else {
-                LOG.info(`YT-DLP Download ${downloadId}`, `yt-dlp exited with code ${data.code}`);
-            }
-        });
-
-        command.on('error', error => {
-            console.error(`Error: ${error}`);
-            LOG.error(`YT-DLP Download ${downloadId}`, `Error occurred: ${error}`);
-            addErroredDownload(downloadId);
-        });
-
-        command.stdout.on('data', async line => {
-            if (line.startsWith('status:') || line.startsWith('[#')) {
-                // console.log(line);
-                if (DEBUG_MODE && LOG_PROGRESS) LOG.progress(`YT-DLP Download ${downloadId}`, line);
-                const currentProgress = await parseProgressLine(line, downloadId);
-                const state: DownloadState = {
-                    download_id: downloadId,
-                    download_status: 'downloading',
-                    video_id: videoId,
-                    format_id: selectedFormat,
-                    subtitle_id: selectedSubtitles || null,
-                    queue_index: null,
-                    playlist_id: playlistId,
-                    playlist_indices: playlistIndices ?? null,
-                    title: videoMetadata.title,
-                    url: url,
-                    host: videoMetadata.webpage_url_domain,
-                    thumbnail: videoMetadata.thumbnail || null,
-                    channel: videoMetadata.creator || videoMetadata.channel || videoMetadata.uploader || null,
-                    duration_string: videoMetadata.duration_string || null,
-                    release_date: videoMetadata.release_date || null,
-                    view_count: videoMetadata.view_count || null,
-                    like_count: videoMetadata.like_count || null,
-                    playlist_title: videoMetadata.playlist_title,
-                    playlist_url: videoMetadata.playlist_webpage_url,
-                    playlist_n_entries: videoMetadata.playlist_count || videoMetadata.n_entries,
-                    playlist_channel: videoMetadata.playlist_channel || null,
-                    resolution: videoMetadata.resolution || null,
-                    ext: videoMetadata.ext || null,
-                    abr: resumeState?.abr || overrideOptions?.tbr/2 || videoMetadata.abr || null,
-                    vbr: resumeState?.vbr || overrideOptions?.tbr/2 || videoMetadata.vbr || null,
-                    acodec: videoMetadata.acodec || null,
-                    vcodec: videoMetadata.vcodec || null,
-                    dynamic_range: videoMetadata.dynamic_range || null,
-                    process_id: processPid,
-                    status: currentProgress.status || null,
-                    item: currentProgress.item || null,
-                    progress: currentProgress.progress || null,
-                    total: currentProgress.total || null,
-                    downloaded: currentProgress.downloaded || null,
-                    speed: currentProgress.speed || null,
-                    eta: currentProgress.eta || null,
-                    filepath: downloadFilePath,
-                    filetype: fileType || null,
-                    filesize: resumeState?.filesize || overrideOptions?.filesize || videoMetadata.filesize_approx || null,
-                    output_format: outputFormat,
-                    embed_metadata: embedMetadata,
-                    embed_thumbnail: embedThumbnail,
-                    square_crop_thumbnail: squareCropThumbnail,
-                    sponsorblock_remove: sponsorblockRemove,
-                    sponsorblock_mark: sponsorblockMark,
-                    use_aria2: useAria2,
-                    custom_command: customCommandArgs,
-                    queue_config: null
-                };
-                updateDownloadProgress(state);
-            } else {
-                // console.log(line);
-                if (line.trim() !== '') LOG.info(`YT-DLP Download ${downloadId}`, line);
-
-                if (isPlaylist && line.startsWith('[download] Downloading item')) {
-                    const playlistItemProgress = extractPlaylistItemProgress(line);
-                    setTimeout(async () => {
-                        playlistItemUpdater.mutate({ download_id: downloadId, item: playlistItemProgress as string }, {
-                            onSuccess: (data) => {
-                                console.log("Playlist item progress updated successfully:", data);
-                                queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            },
-                            onError: (error) => {
-                                console.error("Failed to update playlist item progress:", error);
-                            }
-                        });
-                    }, 1500);
-                }
-
-                if (isPlaylist && line.startsWith('Finalpath: ')) {
-                    downloadFilePath = line.replace('Finalpath: ', '').trim().replace(/^"|"$/g, '');
-                    const downloadedFileExt = downloadFilePath.split('.').pop();
-
-                    const state = await fetchDownloadStateById(downloadId);
-                    const isLastPlaylistItem = state?.item && Number(state?.item.split('/')[0]) === Number(state?.item.split('/')[1]);
-
-                    setTimeout(async () => {
-                        downloadFilePathUpdater.mutate({ download_id: downloadId, filepath: downloadFilePath as string, ext: downloadedFileExt as string }, {
-                            onSuccess: (data) => {
-                                console.log("Download filepath updated successfully:", data);
-                                queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            },
-                            onError: (error) => {
-                                console.error("Failed to update download filepath:", error);
-                            }
-                        });
-
-                        if (isLastPlaylistItem) {
-                            console.log(`Playlist download completed with ID: ${downloadId}, updating status...`);
-
-                            LOG.info('NEODLP', `yt-dlp download completed with id: ${downloadId}`);
-                            downloadStatusUpdater.mutate({ download_id: downloadId, download_status: 'completed' }, {
-                                onSuccess: (data) => {
-                                    console.log("Download status updated successfully:", data);
-                                    queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                                },
-                                onError: (error) => {
-                                    console.error("Failed to update download status:", error);
-                                }
-                            });
-
-                            toast.success("Download Completed", {
-                                description: `The download for ${isMultiplePlaylistItems ? 'playlist ' : ''}"${isMultiplePlaylistItems ? videoMetadata.playlist_title : videoMetadata.title}" has completed successfully.`,
-                            });
-
-                            if (ENABLE_NOTIFICATIONS && DOWNLOAD_COMPLETION_NOTIFICATION) {
-                                sendNotification({
-                                    title: "Download Completed",
-                                    body: `The download for ${isMultiplePlaylistItems ? 'playlist ' : ''}"${isMultiplePlaylistItems ? videoMetadata.playlist_title : videoMetadata.title}" has completed successfully.`,
-                                });
-                            }
-                        }
-                    }, 2000);
-                }
-
-                if (!isPlaylist && line.startsWith('Finalpath: ')) {
-                    downloadFilePath = line.replace('Finalpath: ', '').trim().replace(/^"|"$/g, '');
-                    const downloadedFileExt = downloadFilePath.split('.').pop();
-
-                    // Update completion status after a short delay to ensure database states are propagated correctly
-                    console.log(`Download completed with ID: ${downloadId}, updating filepath and status after 2s delay...`);
-                    setTimeout(async () => {
-                        LOG.info('NEODLP', `yt-dlp download completed with id: ${downloadId}`);
-                        downloadFilePathUpdater.mutate({ download_id: downloadId, filepath: downloadFilePath as string, ext: downloadedFileExt as string }, {
-                            onSuccess: (data) => {
-                                console.log("Download filepath updated successfully:", data);
-                                queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            },
-                            onError: (error) => {
-                                console.error("Failed to update download filepath:", error);
-                            }
-                        });
-
-                        downloadStatusUpdater.mutate({ download_id: downloadId, download_status: 'completed' }, {
-                            onSuccess: (data) => {
-                                console.log("Download status updated successfully:", data);
-                                queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            },
-                            onError: (error) => {
-                                console.error("Failed to update download status:", error);
-                            }
-                        });
-
-                        toast.success("Download Completed", {
-                            description: `The download for "${videoMetadata.title}" has completed successfully.`,
-                        });
-
-                        if (ENABLE_NOTIFICATIONS && DOWNLOAD_COMPLETION_NOTIFICATION) {
-                            sendNotification({
-                                title: "Download Completed",
-                                body: `The download for "${videoMetadata.title}" has completed successfully.`,
-                            });
-                        }
-                    }, 2000);
-                }
-            }
-        });
-
-        command.stderr.on('data', line => {
-            if (line.trim() !== '') LOG.info(`YT-DLP Download ${downloadId}`, line);
-        });
+        const state: DownloadState = {
+            download_id: downloadId,
+            download_status: 'queued',
+            video_id: videoId,
+            format_id: selectedFormat,
+            subtitle_id: selectedSubtitles || null,
+            queue_index: null,
+            playlist_id: playlistId,
+            playlist_indices: playlistIndices ?? null,
+            title: videoMetadata.title,
+            url: url,
+            host: videoMetadata.webpage_url_domain,
+            thumbnail: videoMetadata.thumbnail || null,
+            channel: videoMetadata.creator || videoMetadata.channel || videoMetadata.uploader || null,
+            duration_string: videoMetadata.duration_string || null,
+            release_date: videoMetadata.release_date || null,
+            view_count: videoMetadata.view_count || null,
+            like_count: videoMetadata.like_count || null,
+            playlist_title: videoMetadata.playlist_title,
+            playlist_url: videoMetadata.playlist_webpage_url,
+            playlist_n_entries: videoMetadata.playlist_count || videoMetadata.n_entries,
+            playlist_channel: videoMetadata.playlist_channel || null,
+            resolution: resumeState?.resolution || videoMetadata.resolution || null,
+            ext: resumeState?.ext || videoMetadata.ext || null,
+            abr: resumeState?.abr || overrideOptions?.tbr/2 || videoMetadata.abr || null,
+            vbr: resumeState?.vbr || overrideOptions?.tbr/2 || videoMetadata.vbr || null,
+            acodec: resumeState?.acodec || videoMetadata.acodec || null,
+            vcodec: resumeState?.vcodec || videoMetadata.vcodec || null,
+            dynamic_range: resumeState?.dynamic_range || videoMetadata.dynamic_range || null,
+            process_id: null,
+            status: resumeState?.status || null,
+            item: resumeState?.item || null,
+            progress: resumeState?.progress || null,
+            total: resumeState?.total || null,
+            downloaded: resumeState?.downloaded || null,
+            speed: null,
+            eta: null,
+            filepath: resumeState?.filepath || null,
+            filetype: fileType || null,
+            filesize: resumeState?.filesize || overrideOptions?.filesize || videoMetadata.filesize_approx || null,
+            output_format: outputFormat,
+            embed_metadata: embedMetadata,
+            embed_thumbnail: embedThumbnail,
+            square_crop_thumbnail: squareCropThumbnail,
+            sponsorblock_remove: sponsorblockRemove,
+            sponsorblock_mark: sponsorblockMark,
+            use_aria2: useAria2,
+            custom_command: customCommandArgs,
+            queue_config: resumeState?.queue_config || JSON.stringify(downloadConfig)
+        };
 
         try {
-            videoInfoSaver.mutate({
+            // The row must exist before it can be queued
+            await videoInfoSaver.mutateAsync({
                 video_id: videoId,
                 title: videoMetadata.title,
                 url: url,
@@ -783,104 +550,34 @@ export default function useDownloader() {
                 release_date: videoMetadata.release_date || null,
                 view_count: videoMetadata.view_count || null,
                 like_count: videoMetadata.like_count || null
-            }, {
-                onSuccess: (data) => {
-                    console.log("Video Info saved successfully:", data);
-                    if (isPlaylist) {
-                        playlistInfoSaver.mutate({
-                            playlist_id: playlistId ? playlistId : '',
-                            playlist_title: videoMetadata.playlist_title,
-                            playlist_url: videoMetadata.playlist_webpage_url,
-                            playlist_n_entries: videoMetadata.playlist_count || videoMetadata.n_entries,
-                            playlist_channel: videoMetadata.playlist_creator || videoMetadata.playlist_channel || videoMetadata.playlist_uploader || null
-                        }, {
-                            onSuccess: (data) => {
-                                console.log("Playlist Info saved successfully:", data);
-                            },
-                            onError: (error) => {
-                                console.error("Failed to save playlist info:", error);
-                            }
-                        });
-                    }
-                    const state: DownloadState = {
-                        download_id: downloadId,
-                        download_status: (!ongoingDownloads || ongoingDownloads && ongoingDownloads?.length < MAX_PARALLEL_DOWNLOADS) ? 'starting' : 'queued',
-                        video_id: videoId,
-                        format_id: selectedFormat,
-                        subtitle_id: selectedSubtitles || null,
-                        queue_index: (!ongoingDownloads || ongoingDownloads && ongoingDownloads?.length < MAX_PARALLEL_DOWNLOADS) ? null : (queuedDownloads?.length || 0),
-                        playlist_id: playlistId,
-                        playlist_indices: playlistIndices ?? null,
-                        title: videoMetadata.title,
-                        url: url,
-                        host: videoMetadata.webpage_url_domain,
-                        thumbnail: videoMetadata.thumbnail || null,
-                        channel: videoMetadata.creator || videoMetadata.channel || videoMetadata.uploader || null,
-                        duration_string: videoMetadata.duration_string || null,
-                        release_date: videoMetadata.release_date || null,
-                        view_count: videoMetadata.view_count || null,
-                        like_count: videoMetadata.like_count || null,
-                        playlist_title: videoMetadata.playlist_title,
-                        playlist_url: videoMetadata.playlist_webpage_url,
-                        playlist_n_entries: videoMetadata.playlist_count || videoMetadata.n_entries,
-                        playlist_channel: videoMetadata.playlist_channel || null,
-                        resolution: resumeState?.resolution || null,
-                        ext: resumeState?.ext || null,
-                        abr: resumeState?.abr || overrideOptions?.tbr/2 || null,
-                        vbr: resumeState?.vbr || overrideOptions?.tbr/2 || null,
-                        acodec: resumeState?.acodec || null,
-                        vcodec: resumeState?.vcodec || null,
-                        dynamic_range: resumeState?.dynamic_range || null,
-                        process_id: resumeState?.process_id || null,
-                        status: resumeState?.status || null,
-                        item: resumeState?.item || null,
-                        progress: resumeState?.progress || null,
-                        total: resumeState?.total || null,
-                        downloaded: resumeState?.downloaded || null,
-                        speed: resumeState?.speed || null,
-                        eta: resumeState?.eta || null,
-                        filepath: downloadFilePath,
-                        filetype: resumeState?.filetype || null,
-                        filesize: resumeState?.filesize || overrideOptions?.filesize || null,
-                        output_format: resumeState?.output_format || null,
-                        embed_metadata: resumeState?.embed_metadata || 0,
-                        embed_thumbnail: resumeState?.embed_thumbnail || 0,
-                        square_crop_thumbnail: resumeState?.square_crop_thumbnail || 0,
-                        sponsorblock_remove: resumeState?.sponsorblock_remove || null,
-                        sponsorblock_mark: resumeState?.sponsorblock_mark || null,
-                        use_aria2: resumeState?.use_aria2 || 0,
-                        custom_command: resumeState?.custom_command || null,
-                        queue_config: resumeState?.queue_config || ((!ongoingDownloads || ongoingDownloads && ongoingDownloads?.length < MAX_PARALLEL_DOWNLOADS) ? null : JSON.stringify(downloadConfig))
-                    }
-                    downloadStateSaver.mutate(state, {
-                        onSuccess: (data) => {
-                            console.log("Download State saved successfully:", data);
-                            queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                        },
-                        onError: (error) => {
-                            console.error("Failed to save download state:", error);
-                        }
-                    });
-                },
-                onError: (error) => {
-                    console.error("Failed to save video info:", error);
-                }
             });
-
-            if (!ongoingDownloads || ongoingDownloads && ongoingDownloads?.length < MAX_PARALLEL_DOWNLOADS) {
-                LOG.info('NEODLP', `Starting yt-dlp download with args: ${args.join(' ')}`);
-                if (!DEBUG_MODE || (DEBUG_MODE && !LOG_PROGRESS)) LOG.warning('NEODLP', `Progress logs are hidden. Enable 'Debug Mode > Log Progress' in Settings to unhide.`);
-                const child = await command.spawn();
-                processPid = child.pid;
-                return Promise.resolve();
-            } else {
-                console.log("Download is queued, not starting immediately.");
-                LOG.info('NEODLP', `Download queued with id: ${downloadId}`);
-                return Promise.resolve();
+            if (isPlaylist) {
+                await playlistInfoSaver.mutateAsync({
+                    playlist_id: playlistId ? playlistId : '',
+                    playlist_title: videoMetadata.playlist_title,
+                    playlist_url: videoMetadata.playlist_webpage_url,
+                    playlist_n_entries: videoMetadata.playlist_count || videoMetadata.n_entries,
+                    playlist_channel: videoMetadata.playlist_creator || videoMetadata.playlist_channel || videoMetadata.playlist_uploader || null
+                });
             }
+            await downloadStateSaver.mutateAsync(state);
+            queryClient.invalidateQueries({ queryKey: ['download-states'] });
+        } catch (e) {
+            console.error(`Failed to save download state: ${e}`);
+            LOG.error('NEODLP', `Failed to save download state for URL: ${url} with error: ${e}`);
+            throw e;
+        }
+
+        try {
+            // The backend scheduler starts it as soon as a slot is free, progress and completion are reported through events
+            const queueIndex = await invoke<number>('enqueue_download', { downloadId, args });
+            LOG.info('NEODLP', `Download queued with id: ${downloadId} at index ${queueIndex}, args: ${args.join(' ')}`);
+            if (!DEBUG_MODE || (DEBUG_MODE && !LOG_PROGRESS)) LOG.warning('NEODLP', `Progress logs are hidden. Enable 'Debug Mode > Log Progress' in Settings to unhide.`);
+            queryClient.invalidateQueries({ queryKey: ['download-states'] });
         } catch (e) {
-            console.error(`Failed to start download: ${e}`);
-            LOG.error('NEODLP', `Failed to start download for URL: ${url} with error: ${e}`);
+            console.error(`Failed to queue download: ${e}`);
+            LOG.error('NEODLP', `Failed to queue download for URL: ${url} with error: ${e}`);
+            addErroredDownload(downloadId);
             throw e;
         }
     };
@@ -888,39 +585,13 @@ export default function useDownloader() {
     const pauseDownload = async (downloadState: DownloadState) => {
         try {
             LOG.info('NEODLP', `Pausing yt-dlp download with id: ${downloadState.download_id} (as per user request)`);
-            if ((downloadState.download_status === 'downloading' && downloadState.process_id) || (downloadState.download_status === 'starting' && downloadState.process_id)) {
-                addExpectedErrorDownload(downloadState.download_id); // Mark as error expected to handle UI state
-                console.log("Killing process with PID:", downloadState.process_id);
-                await invoke('kill_all_process', { pid: downloadState.process_id });
-            }
-
-            return new Promise<void>((resolve, reject) => {
-                setTimeout(() => {
-                    downloadStatusUpdater.mutate({ download_id: downloadState.download_id, download_status: 'paused' }, {
-                        onSuccess: (data) => {
-                            console.log("Download status updated successfully:", data);
-                            queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            // Reset the processing flag to ensure queue can be processed
-                            isProcessingQueueRef.current = false;
-                            // Process the queue after a short delay to ensure state is updated
-                            setTimeout(() => {
-                                processQueuedDownloads();
-                            }, 1000);
-                            resolve();
-                        },
-                        onError: (error) => {
-                            console.error("Failed to update download status:", error);
-                            isProcessingQueueRef.current = false;
-                            reject(error);
-                        }
-                    });
-                }, 1500);
-            });
+            // Stops the process (if any) and records the download as paused
+            await invoke('stop_download', { downloadId: downloadState.download_id });
+            await queryClient.invalidateQueries({ queryKey: ['download-states'] });
         } catch (e) {
             console.error(`Failed to pause download: ${e}`);
             LOG.error('NEODLP', `Failed to pause download with id: ${downloadState.download_id} with error: ${e}`);
-            isProcessingQueueRef.current = false;
-            removeExpectedErrorDownload(downloadState.download_id);
+            queryClient.invalidateQueries({ queryKey: ['download-states'] });
             throw e;
         }
     };
@@ -953,115 +624,16 @@ export default function useDownloader() {
     const cancelDownload = async (downloadState: DownloadState) => {
         try {
             LOG.info('NEODLP', `Cancelling yt-dlp download with id: ${downloadState.download_id} (as per user request)`);
-            if ((downloadState.download_status === 'downloading' && downloadState.process_id) || (downloadState.download_status === 'starting' && downloadState.process_id)) {
-                addExpectedErrorDownload(downloadState.download_id); // Mark as error expected to handle UI state
-                console.log("Killing process with PID:", downloadState.process_id);
-                await invoke('kill_all_process', { pid: downloadState.process_id });
-            }
-
-            return new Promise<void>((resolve, reject) => {
-                setTimeout(() => {
-                    downloadStateDeleter.mutate(downloadState.download_id, {
-                        onSuccess: (data) => {
-                            console.log("Download State deleted successfully:", data);
-                            queryClient.invalidateQueries({ queryKey: ['download-states'] });
-                            // Reset processing flag and trigger queue processing
-                            isProcessingQueueRef.current = false;
-
-                            // Process the queue after a short delay
-                            setTimeout(() => {
-                                processQueuedDownloads();
-                            }, 1000);
-                            resolve();
-                        },
-                        onError: (error) => {
-                            console.error("Failed to delete download state:", error);
-                            isProcessingQueueRef.current = false;
-                            reject(error);
-                        }
-                    });
-                }, 1500);
-            });
+            await invoke('stop_download', { downloadId: downloadState.download_id });
+            await downloadStateDeleter.mutateAsync(downloadState.download_id);
+            await queryClient.invalidateQueries({ queryKey: ['download-states'] });
         } catch (e) {
             console.error(`Failed to cancel download: ${e}`);
             LOG.error('NEODLP', `Failed to cancel download with id: ${downloadState.download_id} with error: ${e}`);
-            isProcessingQueueRef.current = false;
-            removeExpectedErrorDownload(downloadState.download_id);
+            queryClient.invalidateQueries({ queryKey: ['download-states'] });
             throw e;
         }
     }
 
-    const processQueuedDownloads = useCallback(async () => {
-        // Prevent concurrent processing
-        if (isProcessingQueueRef.current) {
-            console.log("Queue processing already in progress, skipping...");
-            return;
-        }
-
-        // Check if we can process more downloads
-        if (!queuedDownloads?.length || ongoingDownloads?.length >= MAX_PARALLEL_DOWNLOADS) {
-            return;
-        }
-
-        try {
-            isProcessingQueueRef.current = true;
-            console.log("Processing download queue...");
-
-            // Get the first download in queue
-            const downloadToStart = queuedDownloads[0];
-
-            // Skip if we just processed this download to prevent loops
-            if (lastProcessedDownloadIdRef.current === downloadToStart.download_id) {
-                console.log("Skipping recently processed download:", downloadToStart.download_id);
-                return;
-            }
-
-            // Double-check current state from global state
-            const currentState = globalDownloadStates.find(
-                state => state.download_id === downloadToStart.download_id
-            );
-
-            if (!currentState || currentState.download_status !== 'queued') {
-                console.log("Download no longer in queued state:", downloadToStart.download_id);
-                return;
-            }
-
-            console.log("Starting queued download:", downloadToStart.download_id);
-            LOG.info('NEODLP', `Starting queued download with id: ${downloadToStart.download_id}`);
-            lastProcessedDownloadIdRef.current = downloadToStart.download_id;
-
-            await downloadStatusUpdater.mutateAsync({
-                download_id: downloadToStart.download_id,
-                download_status: 'starting'
-            });
-
-            await queryClient.invalidateQueries({ queryKey: ['download-states'] });
-
-            await startDownload({
-                url: downloadToStart.url,
-                selectedFormat: downloadToStart.format_id,
-                downloadConfig: downloadToStart.queue_config ? JSON.parse(downloadToStart.queue_config) : {
-                    output_format: null,
-                    embed_metadata: null,
-                    embed_thumbnail: null,
-                    square_crop_thumbnail: null,
-                    sponsorblock: null,
-                    custom_command: null
-                },
-                selectedSubtitles: downloadToStart.subtitle_id,
-                resumeState: downloadToStart
-            });
-        } catch (error) {
-            console.error("Error processing download queue:", error);
-            LOG.error('NEODLP', `Error processing download queue: ${error}`);
-        } finally {
-            // Important: reset the processing flag
-            setTimeout(() => {
-                isProcessingQueueRef.current = false;
-                console.log("Queue processor released lock");
-            }, 1000);
-        }
-    }, [queuedDownloads, ongoingDownloads, globalDownloadStates, queryClient]);
-
-    return { fetchVideoMetadata, startDownload, pauseDownload, resumeDownload, cancelDownload, processQueuedDownloads };
+    return { fetchVideoMetadata, startDownload, pauseDownload, resumeDownload, cancelDownload };
 }
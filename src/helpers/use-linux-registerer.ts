@@ -5,6 +5,7 @@ import { useSettingsPageStatesStore } from "@/services/store";
 import { Command } from "@tauri-apps/plugin-shell";
 import { invoke } from "@tauri-apps/api/core";
 import { useYtDlpUpdater } from "@/helpers/use-ytdlp-updater";
+import { NativeHostManifestStatus } from "@/types/nativeHost";
 
 interface FileMap {
     source: string;
@@ -23,21 +24,6 @@ export function useLinuxRegisterer() {
             const isFlatpak = await invoke<boolean>('is_flatpak');
             const resourceDirPath = isFlatpak ? '/app/lib/neodlp' : await resourceDir();
             const homeDirPath = await homeDir();
-            // const flatpakChromeManifestContent = {
-            //     name: "com.neosubhamoy.neodlp",
-            //     description: "NeoDLP MsgHost",
-            //     path: `${homeDirPath}/.local/bin/neodlp-msghost`,
-            //     type: "stdio",
-            //     allowed_origins: ["chrome-extension://mehopeailfjmiloiiohgicphlcgpompf/"]
-            // };
-            // const flatpakFirefoxManifestContent = {
-            //     name: "com.neosubhamoy.neodlp",
-            //     description: "NeoDLP MsgHost",
-            //     path: `${homeDirPath}/.local/bin/neodlp-msghost`,
-            //     type: "stdio",
-            //     allowed_extensions: ["neodlp@neosubhamoy.com"]
-            // };
-
             const filesToCopy: FileMap[] = [
                 { source: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil.py', destination: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil.py', dir: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/' },
                 { source: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil_cli.py', destination: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil_cli.py', dir: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/' },
@@ -45,11 +31,6 @@ export function useLinuxRegisterer() {
             ];
 
             const filesToCopyFlatpak: FileMap[] = [
-                // { source: 'chrome.json', destination: '.config/google-chrome/NativeMessagingHosts/com.neosubhamoy.neodlp.json', dir: '.config/google-chrome/NativeMessagingHosts/', content: JSON.stringify(flatpakChromeManifestContent) },
-                // { source: 'chrome.json', destination: '.config/chromium/NativeMessagingHosts/com.neosubhamoy.neodlp.json', dir: '.config/chromium/NativeMessagingHosts/', content: JSON.stringify(flatpakChromeManifestContent) },
-                // { source: 'firefox.json', destination: '.mozilla/native-messaging-hosts/com.neosubhamoy.neodlp.json', dir: '.mozilla/native-messaging-hosts/', content: JSON.stringify(flatpakFirefoxManifestContent) },
-                // { source: 'neodlp-msghost', destination: '.local/bin/neodlp-msghost', dir: '.local/bin/' },
-
                 { source: 'yt-dlp', destination: '.var/app/com.neosubhamoy.neodlp/data/yt-dlp/yt-dlp', dir: '.var/app/com.neosubhamoy.neodlp/data/yt-dlp/' },
                 { source: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil.py', destination: '.var/app/com.neosubhamoy.neodlp/config/yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil.py', dir: '.var/app/com.neosubhamoy.neodlp/config/yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/' },
                 { source: 'yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil_cli.py', destination: '.var/app/com.neosubhamoy.neodlp/config/yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/getpot_bgutil_cli.py', dir: '.var/app/com.neosubhamoy.neodlp/config/yt-dlp-plugins/bgutil-ytdlp-pot-provider/yt_dlp_plugins/extractor/' },
@@ -103,6 +84,16 @@ export function useLinuxRegisterer() {
                         console.log(`File ${file.source} copied successfully to ${destinationPath}`);
                     }
                 }
+
+                // Not fatal, the deb/rpm packages already register the host system-wide for Chrome, Chromium and Firefox
+                try {
+                    const manifests = await invoke<NativeHostManifestStatus[]>('install_native_host');
+                    for (const manifest of manifests.filter(manifest => manifest.state !== 'browser_not_found')) {
+                        console.log(`Native host manifest for ${manifest.browser} is ${manifest.state} at ${manifest.path}`);
+                    }
+                } catch (error) {
+                    console.warn('Failed to install native host manifests:', error);
+                }
             }
             saveKvPair('linux_registered_version', appVersion);
 
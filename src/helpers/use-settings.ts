@@ -17,6 +17,10 @@ export function useSettings() {
       onSuccess: (data) => {
         setSettingsKey(key, value);
         console.log("Settings key saved successfully:", data);
+        // The backend and neodlp-msghost read download, network and extension settings from the config
+        invoke("sync_config_setting", { key, value }).catch((error) => {
+          console.error(`Error syncing ${key} to config:`, error);
+        });
         queryClient.invalidateQueries({ queryKey: ["settings"] });
       },
       onError: (error) => {
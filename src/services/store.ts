@@ -225,8 +225,11 @@ export const useSettingsPageStatesStore = create<SettingsPageStatesStore>((set)
         restrict_filenames: true,
         unique_filenames: true,
         quit_on_close: false,
+        auto_resume_downloads: true,
+        shutdown_timeout: 10,
         // extension settings
-        websocket_port: 53511
+        websocket_port: 53511,
+        websocket_port_fallback: false
     },
     isUsingDefaultSettings: true,
     isChangingWebSocketPort: false,
@@ -314,8 +317,11 @@ export const useSettingsPageStatesStore = create<SettingsPageStatesStore>((set)
             restrict_filenames: true,
             unique_filenames: true,
             quit_on_close: false,
+            auto_resume_downloads: true,
+            shutdown_timeout: 10,
             // extension settings
-            websocket_port: 53511
+            websocket_port: 53511,
+            websocket_port_fallback: false
         },
         isUsingDefaultSettings: true
     })),
@@ -39,12 +39,12 @@ export interface DownloadState {
     filetype: string | null;
     filesize: number | null;
     output_format: string | null;
-    embed_metadata: number;
-    embed_thumbnail: number;
-    square_crop_thumbnail: number;
+    embed_metadata: boolean;
+    embed_thumbnail: boolean;
+    square_crop_thumbnail: boolean;
     sponsorblock_remove: string | null;
     sponsorblock_mark: string | null;
-    use_aria2: number;
+    use_aria2: boolean;
     custom_command: string | null;
     queue_config: string | null;
     created_at?: string;
@@ -79,12 +79,12 @@ export interface Download {
     filetype: string | null;
     filesize: number | null;
     output_format: string | null;
-    embed_metadata: number;
-    embed_thumbnail: number;
-    square_crop_thumbnail: number;
+    embed_metadata: boolean;
+    embed_thumbnail: boolean;
+    square_crop_thumbnail: boolean;
     sponsorblock_remove: string | null;
     sponsorblock_mark: string | null;
-    use_aria2: number;
+    use_aria2: boolean;
     custom_command: string | null;
     queue_config: string | null;
     created_at: string;
@@ -118,3 +118,14 @@ export interface Paginated<T = any> {
     total: number;
     data: T[];
 }
+
+export interface DownloadProgressEvent extends Omit<DownloadProgress, 'item'> {
+    download_id: string;
+}
+
+export interface DownloadFinishedEvent {
+    download_id: string;
+    exit_code: number | null;
+    download_status: string;
+    filepath: string | null;
+}
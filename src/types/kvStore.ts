@@ -1,6 +1,6 @@
 export interface KvStoreTable {
     key: string;
-    value: string;
+    value: unknown;
 }
 
 export interface KvStore {
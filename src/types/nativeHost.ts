@@ -0,0 +1,8 @@
+export type NativeHostManifestState = 'installed' | 'missing' | 'outdated' | 'invalid' | 'browser_not_found';
+
+// One browser manifest location, from `install_native_host` / `verify_native_host`
+export interface NativeHostManifestStatus {
+    browser: string;
+    path: string;
+    state: NativeHostManifestState;
+}
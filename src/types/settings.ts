@@ -2,7 +2,7 @@ import { ColorScheme, Theme } from "@/providers/themeProvider";
 
 export interface SettingsTable {
     key: string;
-    value: string;
+    value: unknown;
 }
 
 export interface CustomCommand {
@@ -68,8 +68,11 @@ export interface Settings {
     restrict_filenames: boolean;
     unique_filenames: boolean;
     quit_on_close: boolean;
+    auto_resume_downloads: boolean;
+    shutdown_timeout: number;
     // extension settings
     websocket_port: number;
+    websocket_port_fallback: boolean;
 }
 
 export interface DownloadConfiguration {
@@ -80,3 +83,44 @@ export interface DownloadConfiguration {
     sponsorblock: string | null;
     custom_command: string | null;
 }
+
+// Shared with neodlp-msghost, from `get_config` / `patch_config`
+export interface Config {
+    version: number;
+    download: {
+        download_dir: string;
+        max_parallel_downloads: number;
+        max_retries: number;
+        auto_resume_downloads: boolean;
+        filename_template: string;
+        shutdown_timeout: number;
+    };
+    network: {
+        use_proxy: boolean;
+        proxy_url: string;
+        use_rate_limit: boolean;
+        rate_limit: number;
+        use_force_internet_protocol: boolean;
+        force_internet_protocol: 'ipv4' | 'ipv6';
+    };
+    extension: {
+        port: number;
+        port_fallback: boolean;
+        port_range_start: number;
+        port_range_end: number;
+    };
+}
+
+export type ConfigValueSource = 'default' | 'file' | 'environment' | 'argument' | 'fallback';
+
+export interface EffectiveConfigValue<T> {
+    value: T;
+    source: ConfigValueSource;
+}
+
+export interface ConfigInfo extends Config {
+    effective: {
+        port: EffectiveConfigValue<number>;
+        config_path: EffectiveConfigValue<string | null>;
+    };
+}
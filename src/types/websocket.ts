@@ -1,5 +1,31 @@
-export interface WebSocketMessage {
-    url: string;
-    command: string;
-    argument: string;
-}
\ No newline at end of file
+// Requests forwarded by the backend from the browser extension (see the neodlp-protocol crate)
+interface WebSocketRequest {
+    client_id: number;
+    request_id: number;
+}
+
+export type WebSocketMessage = WebSocketRequest & (
+    | { command: 'download'; url: string; argument: string | null }
+    | { command: 'enqueue'; url: string; format: string | null }
+    | { command: 'fetch_info'; url: string }
+    | { command: 'cancel'; download_id: string }
+);
+
+export type WebSocketErrorCode = 'invalid_message' | 'unknown_command' | 'unsupported_version' | 'unauthorized' | 'message_too_large' | 'not_found' | 'timeout' | 'internal';
+
+export type WebSocketResponse =
+    | { type: 'accepted' }
+    | { type: 'info'; info: unknown }
+    | { type: 'cancelled'; download_id: string }
+    | { type: 'error'; code: WebSocketErrorCode; message: string };
+
+// Counters since launch, from `get_websocket_stats`
+export interface WebSocketStats {
+    connections: number;
+    handshake_failures: number;
+    rejected_origins: number;
+    unauthorized: number;
+    invalid_messages: number;
+    emit_failures: number;
+    timeouts: number;
+}